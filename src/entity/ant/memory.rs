@@ -1,10 +1,12 @@
 use semeion::*;
+use serde::{Deserialize, Serialize};
 
 /// Memory of Locations of fixed maximum space.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocationAwareness {
     capacity: usize,
     next: usize,
-    locations: Vec<Option<Location>>,
+    locations: Vec<Option<(i32, i32)>>,
 }
 
 impl LocationAwareness {
@@ -20,14 +22,14 @@ impl LocationAwareness {
     /// Inserts a new Location in place of the oldest one.
     pub fn insert(&mut self, location: Location) {
         if self.capacity > 0 {
-            self.locations[self.next] = Some(location);
+            self.locations[self.next] = Some(location.into());
             self.next = self.next.saturating_add(1).rem_euclid(self.capacity);
         }
     }
 
     /// Returns true only if the given Location is recorded in memory.
     pub fn contains(&self, location: Location) -> bool {
-        self.locations.contains(&Some(location))
+        self.locations.contains(&Some(location.into()))
     }
 
     /// Forgets all the locations.
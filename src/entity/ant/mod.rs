@@ -1,6 +1,7 @@
-use ggez::graphics;
 use rand::{seq::SliceRandom, Rng};
+use rand_chacha::ChaCha8Rng;
 use semeion::*;
+use serde::{Deserialize, Serialize};
 use std::{any::Any, sync::Arc};
 
 use crate::entity::phero;
@@ -10,19 +11,33 @@ use memory::*;
 mod memory;
 
 /// The Ant current activity.
-#[derive(Debug, PartialEq, Eq)]
-enum Activity {
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Activity {
     /// The Ant is is search of food following
     Foraging,
     /// The Ant is carrying a portion of food back to the nest.
     Carrying,
 }
 
-/// The current state of the Ant from the point of view of the neighbor Ants.
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
-pub enum State {
-    Leader,
-    Follower,
+/// The current state of the Ant, which is all the Ant knows besides its
+/// location, as seen by the neighbor Ants and recorded in snapshots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    /// The colony the Ant belongs to.
+    #[serde(default)]
+    colony: entity::Colony,
+    activity: Activity,
+    #[serde(with = "entity::location")]
+    nest_location: Location,
+    phero_concentration: phero::Concentration,
+    memory: LocationAwareness,
+    /// The energy left to the Ant, if it needs to eat to live.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    energy: Option<u64>,
+    #[serde(with = "entity::rng")]
+    rng: ChaCha8Rng,
 }
 
 /// Implement the entity::State trait to allow downcasting when querying the
@@ -37,99 +52,176 @@ impl entity::State for State {
     }
 }
 
-pub struct Ant<'e> {
+pub struct Ant {
     id: entity::Id,
     location: Location,
-    nest_location: Location,
     scope: Scope,
-    activity: Activity,
     state: State,
-    phero_concentration: phero::Concentration,
-    memory: LocationAwareness,
-    offspring: Offspring<'e, entity::Kind, ggez::Context>,
     context: Arc<game::Context>,
 }
 
-impl<'e> Ant<'e> {
-    /// Constructs a new Ant located in its Nest when born.
+impl Ant {
+    /// Constructs a new Ant of the given colony, located in its Nest when
+    /// born.
     pub fn new(
+        colony: entity::Colony,
         location: impl Into<Location>,
         context: Arc<game::Context>,
     ) -> Self {
         let id = context.unique_id();
         let location = location.into();
-        // the Ant can only see its immediate surroundings
-        let scope = Scope::with_magnitude(1);
         // the phero concentration strength left by the Ant is proportional
         // to the distance from the source (Nest/Morsel)
         let phero_concentration =
             context.conf.ants.max_phero_concentration.into();
-        // all the Ants are followers at each step, until decided otherwise
-        let state = State::Follower;
+        let state = State {
+            colony,
+            activity: Activity::Foraging,
+            nest_location: location,
+            phero_concentration,
+            memory: LocationAwareness::new(context.conf.ants.memory_span),
+            energy: context.conf.ants.energy.map(|energy| energy.capacity),
+            // each Ant makes its random decisions from its own stream
+            rng: context.rng(id as u64),
+        };
+
+        Self::restore(id, location, state, context)
+    }
 
+    /// Constructs an Ant with the given ID, location and state, as recorded
+    /// in a snapshot.
+    pub fn restore(
+        id: entity::Id,
+        location: impl Into<Location>,
+        state: State,
+        context: Arc<game::Context>,
+    ) -> Self {
+        // the Ant can only see the tiles within its sight
+        let scope = Scope::with_magnitude(context.conf.ants.sight());
         Self {
             id,
-            location,
-            nest_location: location,
+            location: location.into(),
             scope,
-            activity: Activity::Foraging,
             state,
-            phero_concentration,
-            memory: LocationAwareness::new(context.conf.ants.memory_span),
-            offspring: Offspring::default(),
             context,
         }
     }
 
-    /// Gets the location of the first neighbor Entity of the given Kind, found
-    /// in the immediate surroundings of the Ant.
+    /// Gets the location of the closest neighbor Entity of the given Kind,
+    /// found within the sight of the Ant.
     fn get_location_with_kind(
         &self,
         kind: entity::Kind,
-        neighborhood: &Neighborhood<entity::Kind, ggez::Context>,
+        neighborhood: &Neighborhood<entity::Kind, entity::Context>,
     ) -> Option<Location> {
-        let border = neighborhood
-            .immediate_border(self.scope)
-            .expect("Invalid border");
-        border
-            .iter()
-            .map(|tile| tile.entities().filter(|e| e.kind() == kind))
-            .flatten()
+        self.surroundings(self.context.conf.ants.sight())
+            .into_iter()
+            .map(|(_, offset)| neighborhood.tile(offset))
+            .flat_map(|tile| tile.entities().filter(|&e| is_target(e, kind)))
             .next()
             .and_then(|e| e.location())
     }
 
-    /// Gets the Location of the Tile within the neighborhood of this Ant, that
-    /// contains the Phero entities, which scent strength sum is the greatest,
-    /// unless this Ant has been there before (as far as it can remember).
+    /// Gets the Location of the Tile within the smell of this Ant, that
+    /// contains the pheromone of its colony with the given scent, unless this
+    /// Ant has been there before (as far as it can remember).
+    ///
+    /// With the greedy transition rule the chosen tile is the one which
+    /// concentration, weighted by its distance, is the greatest, while with
+    /// the probabilistic one each tile may be chosen with a probability that
+    /// depends on its concentration and distance.
     fn get_location_with_best_concentration_of(
-        &self,
+        &mut self,
         scent: phero::Scent,
-        neighborhood: &Neighborhood<entity::Kind, ggez::Context>,
     ) -> Option<Location> {
-        let border = neighborhood
-            .immediate_border(self.scope)
-            .expect("Invalid border");
-        border
-            .iter()
-            .filter(|tile| {
+        let env = &self.context.conf.env;
+        let pheros = self.context.pheros();
+        let candidates: Vec<_> = self
+            .surroundings(self.context.conf.ants.smell())
+            .into_iter()
+            .map(|(distance, offset)| {
+                let location = env.topology.translate(
+                    self.location,
+                    offset,
+                    env.dimension,
+                );
+                (location, distance as f64)
+            })
+            .filter(|&(location, _)| {
                 // try to avoid looking in places where the Ant has been already
                 // to avoid getting stuck in local maxima or minima
-                !self.memory.contains(tile.location())
+                !self.state.memory.contains(location)
             })
-            .filter_map(|tile| {
-                let phero_kind = entity::Kind::Phero { scent };
-                debug_assert!(tile.count_kind(phero_kind) <= 1);
-                // get the strength of the Phero entity with the given scent
-                tile.entities()
-                    .find(move |e| e.kind() == phero_kind)
-                    .and_then(|p| p.lifespan())
-                    .and_then(|lifespan| lifespan.length())
-                    .map(|strength| (tile.location(), strength))
+            .map(|(location, distance)| {
+                let strength = pheros.get(scent, self.state.colony, location);
+                (location, distance, strength as f64)
+            })
+            .filter(|&(.., strength)| strength > 0.0)
+            .collect();
+        drop(pheros);
+
+        // find the tile with the overall strongest scent, where the farther
+        // the pheromone the fainter its scent
+        let strongest = candidates
+            .iter()
+            .max_by(|(_, d1, s1), (_, d2, s2)| (s1 / d1).total_cmp(&(s2 / d2)))
+            .map(|&(location, ..)| location);
+
+        match self.context.conf.transition(self.state.colony) {
+            game::Transition::Greedy => strongest,
+            game::Transition::Probabilistic {
+                alpha,
+                beta,
+                exploration,
+            } => {
+                if candidates.is_empty() || self.state.rng.gen_bool(exploration)
+                {
+                    // the Ant ignores the trail to explore the environment
+                    return None;
+                }
+                candidates
+                    .choose_weighted(&mut self.state.rng, |(_, d, s)| {
+                        s.powf(alpha) * d.recip().powf(beta)
+                    })
+                    .map(|&(location, ..)| location)
+                    .ok()
+                    // the weights may be too large to be represented
+                    .or(strongest)
+            }
+        }
+    }
+
+    /// Gets the offsets of the tiles within the given distance from the Ant,
+    /// from the closest to the farthest, each with its distance, except the
+    /// ones beyond the edges of a bounded environment.
+    fn surroundings(&self, radius: usize) -> Vec<(usize, Offset)> {
+        (1..=radius)
+            .flat_map(|distance| {
+                Offset::border(distance)
+                    .into_iter()
+                    .map(move |offset| (distance, offset))
             })
-            // find the tile with the overall strongest scent
-            .max_by_key(|(_, strength)| *strength)
-            .map(|(location, _)| location)
+            .filter(|&(_, offset)| self.is_within(offset))
+            .collect()
+    }
+
+    /// Gets the tiles in the immediate surroundings of the Ant, except the ones
+    /// beyond the edges of a bounded environment.
+    fn border<'n, 'a, 'b>(
+        &self,
+        neighborhood: &'n Neighborhood<'a, 'b, entity::Kind, entity::Context>,
+    ) -> Vec<&'n TileView<'a, 'b, entity::Kind, entity::Context>> {
+        self.surroundings(1)
+            .into_iter()
+            .map(|(_, offset)| neighborhood.tile(offset))
+            .collect()
+    }
+
+    /// Returns true only if the tile at the given offset from the Ant can be
+    /// reached without crossing the edges of a bounded environment.
+    fn is_within(&self, offset: Offset) -> bool {
+        let env = &self.context.conf.env;
+        env.topology.contains(self.location, offset, env.dimension)
     }
 
     /// Move towards the given kind according to the information found in the
@@ -137,7 +229,7 @@ impl<'e> Ant<'e> {
     fn move_towards(
         &mut self,
         kind: entity::Kind,
-        neighborhood: &mut Neighborhood<entity::Kind, ggez::Context>,
+        neighborhood: &mut Neighborhood<entity::Kind, entity::Context>,
     ) {
         // try to find a possible destination that will bring the Ant closer
         // to the destination according to its kind
@@ -149,68 +241,112 @@ impl<'e> Ant<'e> {
             .or_else(|| {
                 self.get_location_with_best_concentration_of(
                     kind.scent().expect("No scent found for kind"),
-                    neighborhood,
                 )
             });
 
         if let Some(dest) = dest {
             // follow the scent of the target pheromone
-            self.location
-                .translate_towards(dest, self.context.conf.env.dimension);
-        } else if self.activity == Activity::Carrying
-            || self.is_lost(neighborhood)
-        {
-            self.move_towards_nest();
+            self.step_towards(dest, neighborhood);
+        } else if self.state.activity == Activity::Carrying || self.is_lost() {
+            self.move_towards_nest(neighborhood);
         } else {
             self.move_randomly(neighborhood);
         }
     }
 
+    /// Gets the Kind the Ant is looking for according to its activity.
+    fn target_kind(&self) -> entity::Kind {
+        self.state.activity.target_kind(self.state.colony)
+    }
+
     /// Returns true only if this Ant cannot release more pheromone and there is
-    /// no trail of pheromones in its neighborhood.
-    fn is_lost(
-        &self,
-        neighborhood: &Neighborhood<entity::Kind, ggez::Context>,
-    ) -> bool {
-        self.phero_concentration.value() == 0
-            && !neighborhood
-                .center()
-                .entities()
-                .any(|e| matches!(e.kind(), entity::Kind::Phero { .. }))
+    /// no trail of pheromones where it's located.
+    fn is_lost(&self) -> bool {
+        self.state.is_lost(&self.context.pheros(), self.location)
+    }
+
+    /// Moves the Ant of a single tile towards the given destination, walking
+    /// around the obstacles in its way.
+    fn step_towards(
+        &mut self,
+        dest: Location,
+        neighborhood: &Neighborhood<entity::Kind, entity::Context>,
+    ) {
+        let env = &self.context.conf.env;
+        let offset = env.topology.step(self.location, dest, env.dimension);
+        if is_walkable(offset, neighborhood) {
+            self.location.translate(offset, env.dimension);
+            return;
+        }
+
+        // make a detour through the free tile that is closest to the
+        // destination, unless the Ant has been there already
+        let mut offsets = Offset::border(1);
+        offsets.shuffle(&mut self.state.rng);
+        let detour = offsets
+            .into_iter()
+            .filter(|&offset| {
+                self.is_within(offset) && is_walkable(offset, neighborhood)
+            })
+            .map(|offset| {
+                *self.location.clone().translate(offset, env.dimension)
+            })
+            .min_by_key(|&location| {
+                (
+                    self.state.memory.contains(location),
+                    env.topology.distance(
+                        location,
+                        dest,
+                        env.dimension,
+                        Distance::Euclidean,
+                    ),
+                )
+            });
+        if let Some(location) = detour {
+            self.location = location;
+        }
     }
 
     /// Moves towards the nest independently of anything else, with a certain
     /// degree of accuracy, proportional to the distance from the Nest.
-    fn move_towards_nest(&mut self) {
-        let mut rng = rand::thread_rng();
-        let dist = self
-            .location
-            .distance(self.nest_location, Distance::Manhattan);
-        debug_assert!(dist > 0);
-        let mut offsets = Offset::border(rng.gen_range(0..dist));
+    fn move_towards_nest(
+        &mut self,
+        neighborhood: &Neighborhood<entity::Kind, entity::Context>,
+    ) {
+        let env = &self.context.conf.env;
+        let dist = env.topology.distance(
+            self.location,
+            self.state.nest_location,
+            env.dimension,
+            Distance::Manhattan,
+        );
+        if dist == 0 {
+            // the Ant may have just picked up food from a Morsel that is
+            // located in the Nest itself
+            return;
+        }
+        let mut offsets = Offset::border(self.state.rng.gen_range(0..dist));
         debug_assert!(!offsets.is_empty());
-        offsets.shuffle(&mut rng);
-
-        let env_dimension = self.context.conf.env.dimension;
-        let dest = *self
-            .nest_location
-            .clone()
-            .translate(offsets[0], env_dimension);
-        self.location.translate_towards(dest, env_dimension);
+        offsets.shuffle(&mut self.state.rng);
+
+        let dest = env.topology.translate(
+            self.state.nest_location,
+            offsets[0],
+            env.dimension,
+        );
+        self.step_towards(dest, neighborhood);
     }
 
     /// Moves the Ant randomly of a single tile, while trying to avoid locations
     /// that already contain the phero left that is related to the current
-    /// activity.
+    /// activity, and never stepping on an obstacle.
     fn move_randomly(
         &mut self,
-        neighborhood: &Neighborhood<entity::Kind, ggez::Context>,
+        neighborhood: &Neighborhood<entity::Kind, entity::Context>,
     ) {
         // all possible neighbors offsets
-        let mut offsets = Offset::border(self.scope);
-
-        let mut rng = rand::thread_rng();
-        offsets.shuffle(&mut rng);
+        let mut offsets = Offset::border(1);
+        offsets.shuffle(&mut self.state.rng);
 
         let offset = offsets
             .iter()
@@ -219,92 +355,61 @@ impl<'e> Ant<'e> {
                 let tile = neighborhood.tile(offset);
                 // try to avoid looking in places where the Ant has been already
                 // to avoid getting stuck in local maxima or minima
-                !self.memory.contains(tile.location())
+                !self.state.memory.contains(tile.location())
+                    && self.is_within(offset)
+                    && is_walkable(offset, neighborhood)
             })
             // if all the surrounding tiles cannot be avoided choose one randomly
             .unwrap_or_else(|| {
-                (rng.gen_range(-1..2), rng.gen_range(-1..2)).into()
+                (
+                    self.state.rng.gen_range(-1..2),
+                    self.state.rng.gen_range(-1..2),
+                )
+                    .into()
             });
 
-        self.location
-            .translate(offset, self.context.conf.env.dimension);
+        // beyond the edges of a bounded environment the Ant is reflected back
+        // or stopped
+        let env = &self.context.conf.env;
+        let offset =
+            env.topology.constrain(self.location, offset, env.dimension);
+        if is_walkable(offset, neighborhood) {
+            self.location.translate(offset, env.dimension);
+        }
     }
 
     /// Leaves the pheromone according to the Ant activity and location.
-    fn enhance_trail_pheromone(
-        &mut self,
-        neighborhood: &mut Neighborhood<entity::Kind, ggez::Context>,
-    ) {
+    fn enhance_trail_pheromone(&mut self) {
         // decrease the concentration of pheromone the Ant can leave at each
         // generation
-        self.phero_concentration
+        self.state
+            .phero_concentration
             .decrease_by(self.context.conf.ants.phero_decrease);
 
-        // check if this tile contains a pheromone entity of the same kind the
-        // Ant is going to leave according to its activity
-        let activity_phero_kind =
-            entity::Kind::phero_with(self.activity.scent());
-        let phero = neighborhood
-            .center_mut()
-            .entities_mut()
-            .find(|e| e.kind() == activity_phero_kind);
-
-        if let Some(phero) = phero {
-            // the tile where the Ant is located already contains the pheromone
-            // that it's supposed to release due to its activity -> simply increase
-            // its concentration instead of releasing a new entity
-            let lifespan = phero.lifespan_mut().expect("Invalid PH lifespan");
-            let length = lifespan.length().expect("Invalid PH lifespan");
-            let mut increase = self.phero_concentration.value() as u64;
-            if self.activity.scent() == phero::Scent::Colony {
-                // reinforce the path that leads to the colony nest
-                increase += (length as f64
-                    * self.context.conf.ants.phero_increase_ratio)
-                    as u64;
-            }
-            lifespan.lengthen_by(increase);
-        } else if self.phero_concentration.value() > 0 {
-            debug_assert_eq!(neighborhood.center().location(), self.location);
-            debug_assert_eq!(
-                neighborhood.center().count_kind(activity_phero_kind),
-                0
+        let scent = self.state.activity.scent();
+        let mut increase = self.state.phero_concentration.value() as u64;
+        if scent == phero::Scent::Colony {
+            // reinforce the path that leads to the colony nest
+            let concentration = self.context.pheros().get(
+                scent,
+                self.state.colony,
+                self.location,
             );
+            increase += (concentration as f64
+                * self.context.conf.ants.phero_increase_ratio)
+                as u64;
+        }
 
-            let is_in_community =
-                neighborhood.center().count_kind(entity::Kind::Ant) > 0;
-            if is_in_community {
-                // we need to build consensus with the other ants that are in
-                // this same tile and may release more pheromone; since we want
-                // at most 1 Phero entity per tile, all the Ants in this one need
-                // to decide who is going to release it
-                let is_under_leader = neighborhood
-                    .center()
-                    .entities()
-                    .filter(|e| e.kind() == entity::Kind::Ant)
-                    .any(|e| {
-                        let state = e
-                            .state()
-                            .and_then(|s| s.as_any().downcast_ref::<State>())
-                            .expect("Invalid state");
-                        state == &State::Leader
-                    });
-
-                if !is_under_leader {
-                    self.state = State::Leader;
-                }
-            }
-
-            if self.state == State::Leader || !is_in_community {
-                // the tile where the Ant is located doesn't contain any pheromone
-                // entity -> release a new Phero entity with a concentration
-                // proportional to the distance from the source (Nest/Morsel)
-                self.offspring.insert(entity::Phero::new(
-                    self.activity.scent(),
-                    self.location,
-                    self.phero_concentration,
-                    Arc::clone(&self.context),
-                ));
-            }
+        // the pheromones left by all the Ants of the same colony located in
+        // the same tile add up to a single concentration
+        if increase > 0 {
+            self.context.deposit(
+                self.id,
+                scent,
+                self.state.colony,
+                self.location,
+                increase,
+            );
         }
     }
 
@@ -312,7 +417,7 @@ impl<'e> Ant<'e> {
     /// it's believed it may be part of a misleading trail.
     fn suppress_trail_pheromone(
         &mut self,
-        neighborhood: &mut Neighborhood<entity::Kind, ggez::Context>,
+        neighborhood: &Neighborhood<entity::Kind, entity::Context>,
     ) {
         // Try to understand if the trail of pheromones the Ant is currently in
         // leads to the target. If the target is not nearby, verify that the
@@ -320,41 +425,38 @@ impl<'e> Ant<'e> {
         // would mean the Ant may be in a misleading trail -> suppress the
         // pheromone in this tile.
 
-        if neighborhood.contains_kind(self.activity.target_kind()) {
+        let target_kind = self.target_kind();
+        let contains_target =
+            |tile: &TileView<entity::Kind, entity::Context>| {
+                tile.entities().any(|e| is_target(e, target_kind))
+            };
+        let is_target_in_sight = self
+            .surroundings(self.context.conf.ants.sight())
+            .into_iter()
+            .any(|(_, offset)| contains_target(neighborhood.tile(offset)));
+        if contains_target(neighborhood.center()) || is_target_in_sight {
             return;
         }
 
-        // check if the tile contains the pheromone entity that would lead
-        // to the Ant target (Nest/Morsel) according to its activity
-        let target_phero_kind =
-            entity::Kind::phero_with(self.activity.target_scent());
-
-        let border = neighborhood
-            .immediate_border(self.scope)
-            .expect("Invalid Border");
-
-        let neighbor_phero_strength = border
+        // check the concentration of the pheromone that would lead to the Ant
+        // target (Nest/Morsel) according to its activity
+        let scent = self.state.activity.target_scent();
+        let colony = self.state.colony;
+        let pheros = self.context.pheros();
+        let neighbor_phero_strength = self
+            .border(neighborhood)
             .iter()
-            .map(|t| t.entities().filter(|e| e.kind() == target_phero_kind))
-            .flatten()
-            .filter_map(|e| e.lifespan().and_then(|l| l.length()))
+            .map(|tile| pheros.get(scent, colony, tile.location()))
             .max()
             .unwrap_or(0);
-
-        let tile_phero_lifespan = neighborhood
-            .center_mut()
-            .entities_mut()
-            .find(|e| e.kind() == target_phero_kind)
-            .and_then(|e| e.lifespan_mut());
-
-        if let Some(tile_phero_lifespan) = tile_phero_lifespan {
-            let strength = tile_phero_lifespan.length().unwrap_or(0);
-            if strength > neighbor_phero_strength {
-                // if the highest pheromone concentration is indeed found in the
-                // current tile while there is no target in the neighborhood, this
-                // trail may be misleading -> clear the pheromone concentration
-                tile_phero_lifespan.clear();
-            }
+        let strength = pheros.get(scent, colony, self.location);
+        drop(pheros);
+
+        if strength > neighbor_phero_strength {
+            // if the highest pheromone concentration is indeed found in the
+            // current tile while there is no target in the neighborhood, this
+            // trail may be misleading -> clear the pheromone concentration
+            self.context.suppress(self.id, scent, colony, self.location);
         }
     }
 
@@ -362,59 +464,127 @@ impl<'e> Ant<'e> {
     /// actions accordingly.
     fn assess_location_for_targets(
         &mut self,
-        neighborhood: &mut Neighborhood<entity::Kind, ggez::Context>,
+        neighborhood: &mut Neighborhood<entity::Kind, entity::Context>,
     ) {
-        // check if the Ant is in the same location of a possible target
-        for &target in &[entity::Kind::Nest, entity::Kind::Morsel] {
+        // check if the Ant is in the same location of a possible target, while
+        // the Nests of the other colonies are ignored
+        let nest = entity::Kind::Nest {
+            colony: self.state.colony,
+        };
+        for &target in &[nest, entity::Kind::Morsel] {
             let target_entity = get_overlapping_kind_mut(target, neighborhood);
             if let Some(target_entity) = target_entity {
                 debug_assert_eq!(target_entity.kind(), target);
-                debug_assert_eq!(self.location(), target_entity.location());
+                // a Nest being moved may already have its new location, while
+                // still in the tile it's moving away from
+                debug_assert!(
+                    target == nest
+                        || self.location() == target_entity.location()
+                );
 
                 // drop the food into the nest
-                if self.activity == Activity::Carrying
-                    && target == entity::Kind::Nest
-                {
-                    target_entity
-                        .state_mut()
-                        .and_then(|s| {
-                            s.as_any_mut().downcast_mut::<entity::nest::State>()
-                        })
-                        .expect("Cannot get Nest state")
-                        .store();
+                if self.state.activity == Activity::Carrying && target == nest {
+                    entity::downcast_state_mut::<entity::nest::State>(
+                        target_entity,
+                    )
+                    .store();
+                    self.context.record(game::Event::DropOff { ant: self.id });
+                }
+
+                if self.is_hungry() {
+                    self.eat(target_entity);
                 }
 
                 // if the Ant reached its target, switch its activity and reset
                 // it memory
-                if target == self.activity.target_kind() {
+                if target == self.target_kind() {
                     // there may be more than a single Ant in this Morsel and we
                     // must avoid taking more food than it actually stores
                     if target == entity::Kind::Morsel {
-                        let lifespan = target_entity
-                            .lifespan_mut()
-                            .expect("Invalid Morsel lifespan");
-                        if lifespan.is_alive() {
-                            lifespan.shorten();
-                            self.activity.switch();
-                            self.memory.clear();
+                        let morsel = target_entity.id();
+                        let state: &mut entity::morsel::State =
+                            entity::downcast_state_mut(target_entity);
+                        if state.take() {
+                            self.context.record(game::Event::Pickup {
+                                ant: self.id,
+                                morsel,
+                            });
+                            self.state.activity.switch();
+                            self.state.memory.clear();
                         }
                     } else {
-                        self.activity.switch();
-                        self.memory.clear();
+                        self.state.activity.switch();
+                        self.state.memory.clear();
                     }
                 }
 
                 // reset the pheromone concentration
-                self.phero_concentration =
+                self.state.phero_concentration =
                     self.context.conf.ants.max_phero_concentration.into();
             }
         }
     }
+
+    /// Returns true only if the energy of the Ant fell below its hunger
+    /// threshold.
+    fn is_hungry(&self) -> bool {
+        match (self.state.energy, self.context.conf.ants.energy) {
+            (Some(energy), Some(conf)) => {
+                (energy as f64) < conf.capacity as f64 * conf.hunger
+            }
+            _ => false,
+        }
+    }
+
+    /// Eats a unit of food from the given Nest or Morsel, if any is left, to
+    /// refill the energy of the Ant.
+    fn eat(&mut self, target: &mut EntityTrait<entity::Kind, entity::Context>) {
+        let morsel = match target.kind() {
+            entity::Kind::Morsel => {
+                let id = target.id();
+                let morsel: &mut entity::morsel::State =
+                    entity::downcast_state_mut(target);
+                if !morsel.take() {
+                    return;
+                }
+                Some(id)
+            }
+            _ => {
+                let nest: &mut entity::nest::State =
+                    entity::downcast_state_mut(target);
+                if !nest.take() {
+                    return;
+                }
+                None
+            }
+        };
+
+        self.state.energy = self.context.conf.ants.energy.map(|e| e.capacity);
+        self.context.record(game::Event::Eat {
+            ant: self.id,
+            morsel,
+        });
+    }
+
+    /// Spends the energy needed to move to an adjacent tile, the Ant dies of
+    /// hunger when it has no energy left.
+    fn spend_energy(&mut self) {
+        let cost = match self.context.conf.ants.energy {
+            Some(conf) => conf.move_cost,
+            None => return,
+        };
+        if let Some(energy) = &mut self.state.energy {
+            *energy = energy.saturating_sub(cost);
+            if *energy == 0 {
+                self.context.record_death(self.state.colony, self.id);
+            }
+        }
+    }
 }
 
-impl<'e> Entity<'e> for Ant<'e> {
+impl<'e> Entity<'e> for Ant {
     type Kind = entity::Kind;
-    type Context = ggez::Context;
+    type Context = entity::Context;
 
     fn id(&self) -> entity::Id {
         self.id
@@ -436,63 +606,77 @@ impl<'e> Entity<'e> for Ant<'e> {
         Some(&self.state)
     }
 
+    fn state_mut(&mut self) -> Option<&mut dyn entity::State> {
+        Some(&mut self.state)
+    }
+
     fn react(
         &mut self,
         neighborhood: Option<Neighborhood<'_, 'e, Self::Kind, Self::Context>>,
     ) -> Result<(), Error> {
         let mut neighborhood = neighborhood.expect("Invalid neighborhood");
 
-        self.state = State::Follower;
-        self.memory.insert(self.location);
+        self.state.memory.insert(self.location);
 
         self.assess_location_for_targets(&mut neighborhood);
-        self.enhance_trail_pheromone(&mut neighborhood);
-        self.suppress_trail_pheromone(&mut neighborhood);
-        self.move_towards(self.activity.target_kind(), &mut neighborhood);
+        self.enhance_trail_pheromone();
+        self.suppress_trail_pheromone(&neighborhood);
+        let location = self.location;
+        self.move_towards(self.target_kind(), &mut neighborhood);
+        if self.location != location {
+            self.context.record(game::Event::Move {
+                ant: self.id,
+                to: self.location,
+            });
+            self.spend_energy();
+        }
 
         Ok(())
     }
 
-    fn offspring(
-        &mut self,
-    ) -> Option<Offspring<'e, Self::Kind, Self::Context>> {
-        // the Ant can release at most 1 Phero entity per generation
-        debug_assert!(self.offspring.count() <= 1);
-        Some(self.offspring.drain())
+    fn lifespan(&self) -> Option<Lifespan> {
+        // the Ant dies as soon as it runs out of energy
+        self.state.energy.map(Lifespan::with_span)
     }
+}
 
-    fn draw(
-        &self,
-        ctx: &mut Self::Context,
-        mut transform: Transform,
-    ) -> Result<(), Error> {
-        if !self.context.conf.is_visible(&self.kind()) {
-            return Ok(());
-        }
+impl State {
+    /// Gets the colony the Ant belongs to.
+    pub fn colony(&self) -> entity::Colony {
+        self.colony
+    }
 
-        // shift the center of the mesh to the center of the Tile
-        let env_side = self.context.conf.env.tile_side;
-        let entity_size =
-            entity::size(self.kind(), self.context.conf.env.tile_side);
-        let center_offset = entity_size / 2.0 - env_side / 2.0;
-        let loc = self.location.to_pixel_coords(env_side) - center_offset;
-        // translate according to the current entity location
-        transform *= Transform::translate(loc);
-
-        let mesh = self.context.kind_mesh(&self.kind());
-        let color = match self.activity {
-            Activity::Foraging => [1.0, 0.0, 0.0, 1.0],
-            Activity::Carrying => [0.0, 0.0, 1.0, 1.0],
-        };
+    /// Gets the current activity of the Ant.
+    pub fn activity(&self) -> Activity {
+        self.activity
+    }
+
+    /// Gets the location of the Nest the Ant brings the food back to.
+    pub fn nest_location(&self) -> Location {
+        self.nest_location
+    }
+
+    /// Gets the energy left to the Ant, if it needs to eat to live.
+    pub fn energy(&self) -> Option<u64> {
+        self.energy
+    }
+
+    /// Sets the location of the Nest the Ant brings the food back to.
+    pub fn set_nest_location(&mut self, location: impl Into<Location>) {
+        self.nest_location = location.into();
+    }
 
-        graphics::draw(
-            ctx,
-            mesh,
-            graphics::DrawParam::default()
-                .transform(transform.to_column_matrix4())
-                .color(color.into()),
-        )
-        .map_err(Error::with_message)
+    /// Returns true only if the Ant cannot release more pheromone and there is
+    /// no pheromone of its colony, of any scent, at the given location.
+    pub fn is_lost(
+        &self,
+        pheros: &game::PheroField,
+        location: Location,
+    ) -> bool {
+        self.phero_concentration.value() == 0
+            && [phero::Scent::Colony, phero::Scent::Food]
+                .iter()
+                .all(|&scent| pheros.get(scent, self.colony, location) == 0)
     }
 }
 
@@ -506,17 +690,19 @@ impl Activity {
         }
     }
 
-    /// Each Activity has a corresponding Kind as a target.
-    fn target_kind(&self) -> entity::Kind {
+    /// Each Activity has a corresponding Kind as a target, where the Nest is
+    /// the one of the given colony.
+    fn target_kind(&self, colony: entity::Colony) -> entity::Kind {
         match self {
-            Activity::Carrying => entity::Kind::Nest,
+            Activity::Carrying => entity::Kind::Nest { colony },
             Activity::Foraging => entity::Kind::Morsel,
         }
     }
 
     /// Each activity target has a Scent.
     fn target_scent(&self) -> phero::Scent {
-        self.target_kind()
+        // the scent doesn't depend on the colony
+        self.target_kind(entity::Colony::default())
             .scent()
             .expect("Cannot find scent for kind")
     }
@@ -530,30 +716,40 @@ impl Activity {
     }
 }
 
-/// Gets the first Entity of the given Kind that is located in the same
-/// location of this Ant.
+/// Returns true only if the tile located at the given offset from the center
+/// of the neighborhood is not an obstacle.
+fn is_walkable(
+    offset: Offset,
+    neighborhood: &Neighborhood<entity::Kind, entity::Context>,
+) -> bool {
+    !neighborhood
+        .tile(offset)
+        .contains_kind(entity::Kind::Obstacle)
+}
+
+/// Gets the Entity of the given Kind with the lowest ID that is located in the
+/// same location of this Ant, since the entities of a tile are found in
+/// arbitrary order.
 fn get_overlapping_kind_mut<'n, 'e>(
     kind: entity::Kind,
-    neighborhood: &'n mut Neighborhood<'_, 'e, entity::Kind, ggez::Context>,
-) -> Option<&'n mut EntityTrait<'e, entity::Kind, ggez::Context>> {
+    neighborhood: &'n mut Neighborhood<'_, 'e, entity::Kind, entity::Context>,
+) -> Option<&'n mut EntityTrait<'e, entity::Kind, entity::Context>> {
     neighborhood
         .center_mut()
         .entities_mut()
-        .find(|e| e.kind() == kind)
+        .filter(|e| is_target(&**e, kind))
+        .min_by_key(|e| e.id())
 }
 
-/// Constructs a new mesh for an Ant.
-pub fn mesh(
-    ctx: &mut ggez::Context,
-    conf: &game::Conf,
-) -> ggez::GameResult<graphics::Mesh> {
-    let color = graphics::Color::WHITE;
-    let entity_size = entity::size(entity::Kind::Ant, conf.env.tile_side);
-    let tolerance = 2.0;
-    let radius = entity_size / 2.0;
-    let center = [radius, radius];
-
-    let mut mesh = graphics::MeshBuilder::new();
-    mesh.circle(graphics::DrawMode::fill(), center, radius, tolerance, color)?;
-    mesh.build(ctx)
+/// Returns true only if the given Entity is a target of the given Kind, that
+/// is not a Morsel left empty, which the Ants ignore until it regrows.
+fn is_target(
+    entity: &EntityTrait<entity::Kind, entity::Context>,
+    kind: entity::Kind,
+) -> bool {
+    entity.kind() == kind
+        && (kind != entity::Kind::Morsel
+            || entity::downcast_state::<entity::morsel::State>(entity)
+                .storage()
+                > 0)
 }
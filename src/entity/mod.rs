@@ -1,18 +1,30 @@
 use semeion::*;
+// disambiguate from the State of the entities glob imported below
+use semeion::State;
 use serde::{Deserialize, Serialize};
 
 pub use ant::*;
-pub use grid::*;
 pub use morsel::*;
 pub use nest::*;
+pub use obstacle::*;
 pub use phero::*;
 
 pub mod ant;
-pub mod grid;
 pub mod morsel;
 pub mod nest;
+pub mod obstacle;
 pub mod phero;
 
+/// The context the entities are drawn with.
+///
+/// Rendering is not part of the simulation: the entities are never drawn
+/// through their Entity trait, but by the graphical interface (if any) that
+/// queries their current state instead.
+pub type Context = ();
+
+/// The index of a colony among the colonies of the configuration.
+pub type Colony = usize;
+
 /// The kinds of all the entities.
 #[derive(
     Debug,
@@ -28,37 +40,101 @@ pub mod phero;
 )]
 #[serde(rename_all = "camelCase")]
 pub enum Kind {
-    Grid,
-    Phero { scent: phero::Scent },
-    Nest,
+    Nest { colony: Colony },
     Morsel,
     Ant,
+    Obstacle,
 }
 
 impl Kind {
-    /// Creates a new kind of Phero with the Scent of food.
-    pub fn phero_with(scent: phero::Scent) -> Self {
-        Self::Phero { scent }
-    }
-
     /// Gets the phero Scent used to seek the Kind of self.
     pub fn scent(&self) -> Option<phero::Scent> {
         match self {
-            Self::Nest => Some(phero::Scent::Colony),
+            Self::Nest { .. } => Some(phero::Scent::Colony),
             Self::Morsel => Some(phero::Scent::Food),
             _ => None,
         }
     }
 }
 
-/// Gets the size of the entity in number of pixels according to the given
-/// tile side.
-fn size(kind: Kind, side: f32) -> f32 {
-    match kind {
-        Kind::Grid => 0.0,
-        Kind::Nest => side + side * 0.1,
-        Kind::Morsel => side + side * 0.1,
-        Kind::Phero { .. } => side,
-        Kind::Ant => side - side * 0.2,
+/// Gets the state of the given Entity, which type is known from its kind.
+pub(crate) fn downcast_state<'a, T: 'static>(
+    entity: &'a EntityTrait<Kind, Context>,
+) -> &'a T {
+    entity
+        .state()
+        .and_then(|s| s.as_any().downcast_ref::<T>())
+        .unwrap_or_else(|| panic!("Invalid state for {:?}", entity.kind()))
+}
+
+/// Gets the mutable state of the given Entity, which type is known from its
+/// kind.
+pub(crate) fn downcast_state_mut<'a, T: 'static>(
+    entity: &'a mut EntityTrait<Kind, Context>,
+) -> &'a mut T {
+    let kind = entity.kind();
+    entity
+        .state_mut()
+        .and_then(|s| s.as_any_mut().downcast_mut::<T>())
+        .unwrap_or_else(|| panic!("Invalid state for {:?}", kind))
+}
+
+/// Serialization of a Location as a pair of coordinates.
+pub mod location {
+    use semeion::Location;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        location: &Location,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        <(i32, i32)>::from(*location).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Location, D::Error> {
+        <(i32, i32)>::deserialize(deserializer).map(Location::from)
+    }
+}
+
+/// Serialization of the state of a ChaCha random number generator, which word
+/// position (a 128 bit integer) is split in its high and low 64 bits, since
+/// not every format supports 128 bit integers.
+pub mod rng {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct RngState {
+        seed: [u8; 32],
+        stream: u64,
+        word_pos: (u64, u64),
+    }
+
+    pub fn serialize<S: Serializer>(
+        rng: &ChaCha8Rng,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let word_pos = rng.get_word_pos();
+        RngState {
+            seed: rng.get_seed(),
+            stream: rng.get_stream(),
+            word_pos: ((word_pos >> 64) as u64, word_pos as u64),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ChaCha8Rng, D::Error> {
+        let state = RngState::deserialize(deserializer)?;
+        let mut rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        let (high, low) = state.word_pos;
+        rng.set_word_pos((high as u128) << 64 | low as u128);
+        Ok(rng)
     }
 }
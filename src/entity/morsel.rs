@@ -1,37 +1,67 @@
-use ggez::graphics;
 use semeion::*;
-use std::sync::Arc;
+use std::{any::Any, sync::Arc};
 
 use crate::{entity, game};
 
+/// The current state of the Morsel from the point of view of the Ants.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct State {
+    storage: u64,
+    /// Whether the Morsel regrows its food, and so stays in the environment
+    /// once emptied, until removed.
+    regrows: bool,
+}
+
+/// Implement the entity::State trait to allow downcasting when querying the
+/// Morsel state via the Entity::state() method.
+impl entity::State for State {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
 /// A static morsel.
 pub struct Morsel {
     id: entity::Id,
     location: Location,
-    lifespan: Lifespan,
-    context: Arc<game::Context>,
+    state: State,
 }
 
 impl Morsel {
-    /// Constructs a new Morsel.
+    /// Constructs a new Morsel storing the given amount of food.
     pub fn new(
         location: impl Into<Location>,
-        lifespan: impl Into<Lifespan>,
+        storage: u64,
         context: Arc<game::Context>,
     ) -> Self {
-        let id = context.unique_id();
+        Self::restore(context.unique_id(), location, storage, &context)
+    }
+
+    /// Constructs a Morsel with the given ID, as recorded in a snapshot.
+    pub fn restore(
+        id: entity::Id,
+        location: impl Into<Location>,
+        storage: u64,
+        context: &game::Context,
+    ) -> Self {
         Self {
             id,
             location: location.into(),
-            lifespan: lifespan.into(),
-            context,
+            state: State {
+                storage,
+                regrows: context.conf.morsels.regrowth.is_some(),
+            },
         }
     }
 }
 
 impl<'e> Entity<'e> for Morsel {
     type Kind = entity::Kind;
-    type Context = ggez::Context;
+    type Context = entity::Context;
 
     fn id(&self) -> entity::Id {
         self.id
@@ -46,73 +76,46 @@ impl<'e> Entity<'e> for Morsel {
     }
 
     fn lifespan(&self) -> Option<Lifespan> {
-        Some(self.lifespan)
+        // the Morsel is removed once emptied, unless it regrows its food
+        if self.state.regrows {
+            Some(Lifespan::Immortal)
+        } else {
+            Some(Lifespan::with_span(self.state.storage))
+        }
     }
 
-    fn lifespan_mut(&mut self) -> Option<&mut Lifespan> {
-        Some(&mut self.lifespan)
+    fn state(&self) -> Option<&dyn entity::State> {
+        Some(&self.state)
     }
 
-    fn draw(
-        &self,
-        ctx: &mut Self::Context,
-        mut transform: Transform,
-    ) -> Result<(), Error> {
-        if !self.context.conf.is_visible(&self.kind()) {
-            return Ok(());
-        }
-
-        // shift the center of the Rect to the center of the Tile
-        let env_side = self.context.conf.env.tile_side;
-        let entity_size =
-            entity::size(self.kind(), self.context.conf.env.tile_side);
-        let center_offset = entity_size / 2.0 - env_side / 2.0;
-        let loc = self.location.to_pixel_coords(env_side) - center_offset;
-        // translate according to the current entity location
-        let translation = Transform::translate(loc);
-
-        // scale according to a value proportional to the remaining lifespan
-        let lifespan = self.lifespan.length().unwrap_or(0) as f32;
-        let max_concentration = self.context.conf.morsels.storage as f32;
-        let scale = (lifespan / max_concentration).min(1.0);
-        let scale = Transform::scale_around(
-            [scale, scale],
-            [entity_size / 2.0, entity_size / 2.0],
-        );
-
-        transform *= translation * scale;
-
-        let mesh = self.context.kind_mesh(&self.kind());
-        graphics::draw(
-            ctx,
-            mesh,
-            graphics::DrawParam::default()
-                .transform(transform.to_column_matrix4()),
-        )
-        .map_err(Error::with_message)
+    fn state_mut(&mut self) -> Option<&mut dyn entity::State> {
+        Some(&mut self.state)
     }
 }
 
-/// Constructs a new mesh for a Morsel.
-pub fn mesh(
-    ctx: &mut ggez::Context,
-    conf: &game::Conf,
-) -> ggez::GameResult<graphics::Mesh> {
-    let mut mesh = graphics::MeshBuilder::new();
-    let color = graphics::Color::new(0.3, 0.5, 0.0, 1.0);
-    let entity_size = entity::size(entity::Kind::Morsel, conf.env.tile_side);
-
-    let outer = graphics::Rect::new(0.0, 0.0, entity_size, entity_size);
-    mesh.rectangle(graphics::DrawMode::stroke(1.0), outer, color)?;
-
-    let half_size = entity_size / 2.0;
-    let inner = graphics::Rect::new(
-        half_size / 2.0,
-        half_size / 2.0,
-        half_size,
-        half_size,
-    );
-    mesh.rectangle(graphics::DrawMode::fill(), inner, color)?;
-
-    mesh.build(ctx)
+impl State {
+    /// Takes a single unit of food from the storage, returns false if the
+    /// storage is empty.
+    pub fn take(&mut self) -> bool {
+        let is_empty = self.storage == 0;
+        self.storage = self.storage.saturating_sub(1);
+        !is_empty
+    }
+
+    /// Adds the given amount of food to the storage.
+    pub fn grow(&mut self, amount: u64) {
+        self.storage = self.storage.saturating_add(amount);
+    }
+
+    /// Gets the amount of food stored.
+    pub fn storage(&self) -> u64 {
+        self.storage
+    }
+
+    /// Empties the Morsel, which is removed from the environment in the next
+    /// generation even if it regrows its food.
+    pub fn remove(&mut self) {
+        self.storage = 0;
+        self.regrows = false;
+    }
 }
@@ -1,13 +1,22 @@
-use ggez::graphics;
 use semeion::*;
+use serde::{Deserialize, Serialize};
 use std::{any::Any, sync::Arc};
 
 use crate::{entity, game};
 
-/// The current state of the Ant from the point of view of the neighbor Ants.
-#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+/// The current state of the Nest from the point of view of the Ants.
+#[derive(
+    Debug, Eq, PartialEq, Clone, Copy, Default, Serialize, Deserialize,
+)]
 pub struct State {
     storage: u64,
+    /// The total amount of food delivered to the Nest, including the food
+    /// taken from its storage since then.
+    #[serde(default)]
+    delivered: u64,
+    /// The location the Nest is going to be moved to in the next generation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    relocation: Option<(i32, i32)>,
 }
 
 /// Implement the entity::State trait to allow downcasting when querying the
@@ -22,42 +31,58 @@ impl entity::State for State {
     }
 }
 
-/// A static nest.
+/// A static nest, home of a colony of Ants.
 pub struct Nest {
     id: entity::Id,
+    colony: entity::Colony,
     location: Location,
-    context: Arc<game::Context>,
     state: State,
+    context: Arc<game::Context>,
 }
 
 impl Nest {
-    /// Constructs a new Nest.
+    /// Constructs a new Nest of the given colony.
     pub fn new(
+        colony: entity::Colony,
         location: impl Into<Location>,
         context: Arc<game::Context>,
     ) -> Self {
-        let id = context.unique_id();
         // the storage of food is initially empty
-        let state = State::default();
+        let id = context.unique_id();
+        Self::restore(id, colony, location, State::default(), context)
+    }
+
+    /// Constructs a Nest with the given ID and state, as recorded in a
+    /// snapshot.
+    pub fn restore(
+        id: entity::Id,
+        colony: entity::Colony,
+        location: impl Into<Location>,
+        state: State,
+        context: Arc<game::Context>,
+    ) -> Self {
         Self {
             id,
+            colony,
             location: location.into(),
-            context,
             state,
+            context,
         }
     }
 }
 
 impl<'e> Entity<'e> for Nest {
     type Kind = entity::Kind;
-    type Context = ggez::Context;
+    type Context = entity::Context;
 
     fn id(&self) -> entity::Id {
         self.id
     }
 
     fn kind(&self) -> Self::Kind {
-        entity::Kind::Nest
+        entity::Kind::Nest {
+            colony: self.colony,
+        }
     }
 
     fn location(&self) -> Option<Location> {
@@ -72,34 +97,45 @@ impl<'e> Entity<'e> for Nest {
         Some(&mut self.state)
     }
 
-    fn draw(
-        &self,
-        ctx: &mut Self::Context,
-        mut transform: Transform,
+    fn react(
+        &mut self,
+        _: Option<Neighborhood<Self::Kind, Self::Context>>,
     ) -> Result<(), Error> {
-        if !self.context.conf.is_visible(&self.kind()) {
-            return Ok(());
+        if let Some(location) = self.state.relocation.take() {
+            self.location = location.into();
+        }
+        Ok(())
+    }
+
+    /// Spawns a new Ant for each amount of food per Ant the Nest can take from
+    /// its storage, as long as the colony is below its maximum population.
+    ///
+    /// The Ants are spawned once all the entities reacted, so that the Ants
+    /// that died of hunger in this generation are already accounted for.
+    fn offspring(
+        &mut self,
+    ) -> Option<Offspring<'e, Self::Kind, Self::Context>> {
+        let growth = self.context.conf.nest.growth?;
+        let population = self.context.population(self.colony);
+        let room = growth.max_population.saturating_sub(population) as u64;
+        let count = (self.state.storage / growth.food_per_ant).min(room);
+        if count == 0 {
+            return None;
         }
 
-        // shift the center of the Rect to the center of the Tile
-        let env_side = self.context.conf.env.tile_side;
-        let entity_size =
-            entity::size(self.kind(), self.context.conf.env.tile_side);
-        let center_offset = entity_size / 2.0 - env_side / 2.0;
-        let loc = self.location.to_pixel_coords(env_side) - center_offset;
-        // translate according to the current entity location
-        let translation = Transform::translate(loc);
-
-        transform *= translation;
-
-        let mesh = self.context.kind_mesh(&self.kind());
-        graphics::draw(
-            ctx,
-            mesh,
-            graphics::DrawParam::default()
-                .transform(transform.to_column_matrix4()),
-        )
-        .map_err(Error::with_message)
+        let mut offspring = Offspring::with_capacity(count as usize);
+        for _ in 0..count {
+            self.state.storage -= growth.food_per_ant;
+            let ant = entity::Ant::new(
+                self.colony,
+                self.location,
+                Arc::clone(&self.context),
+            );
+            self.context
+                .record_birth(self.colony, ant.id(), self.location);
+            offspring.insert(ant);
+        }
+        Some(offspring)
     }
 }
 
@@ -107,34 +143,30 @@ impl State {
     /// Increment the food storage by a single unit.
     pub fn store(&mut self) {
         self.storage = self.storage.saturating_add(1);
+        self.delivered = self.delivered.saturating_add(1);
+    }
+
+    /// Takes a single unit of food from the storage, returns false if the
+    /// storage is empty.
+    pub fn take(&mut self) -> bool {
+        let is_empty = self.storage == 0;
+        self.storage = self.storage.saturating_sub(1);
+        !is_empty
     }
 
     /// Gets the total amount of food stored.
     pub fn storage(&self) -> u64 {
         self.storage
     }
-}
 
-/// Constructs a new mesh for a Nest.
-pub fn mesh(
-    ctx: &mut ggez::Context,
-    conf: &game::Conf,
-) -> ggez::GameResult<graphics::Mesh> {
-    let mut mesh = graphics::MeshBuilder::new();
-    let color = graphics::Color::new(0.1, 0.3, 0.9, 1.0);
-
-    let entity_size = entity::size(entity::Kind::Nest, conf.env.tile_side);
-    let outer = graphics::Rect::new(0.0, 0.0, entity_size, entity_size);
-    mesh.rectangle(graphics::DrawMode::stroke(3.0), outer, color)?;
-
-    let half_size = entity_size / 2.0;
-    let inner = graphics::Rect::new(
-        half_size / 2.0,
-        half_size / 2.0,
-        half_size,
-        half_size,
-    );
-    mesh.rectangle(graphics::DrawMode::fill(), inner, color)?;
-
-    mesh.build(ctx)
+    /// Gets the total amount of food delivered so far, that doesn't decrease
+    /// when food is taken from the storage.
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Moves the Nest to the given location in the next generation.
+    pub fn relocate(&mut self, location: impl Into<Location>) {
+        self.relocation = Some(location.into().into());
+    }
 }
@@ -0,0 +1,45 @@
+use semeion::*;
+use std::sync::Arc;
+
+use crate::{entity, game};
+
+/// A static obstacle, that the Ants cannot walk on.
+pub struct Obstacle {
+    id: entity::Id,
+    location: Location,
+}
+
+impl Obstacle {
+    /// Constructs a new Obstacle.
+    pub fn new(
+        location: impl Into<Location>,
+        context: Arc<game::Context>,
+    ) -> Self {
+        Self::restore(context.unique_id(), location)
+    }
+
+    /// Constructs an Obstacle with the given ID, as recorded in a snapshot.
+    pub fn restore(id: entity::Id, location: impl Into<Location>) -> Self {
+        Self {
+            id,
+            location: location.into(),
+        }
+    }
+}
+
+impl<'e> Entity<'e> for Obstacle {
+    type Kind = entity::Kind;
+    type Context = entity::Context;
+
+    fn id(&self) -> entity::Id {
+        self.id
+    }
+
+    fn kind(&self) -> Self::Kind {
+        entity::Kind::Obstacle
+    }
+
+    fn location(&self) -> Option<Location> {
+        Some(self.location)
+    }
+}
@@ -1,21 +1,61 @@
-use anyhow::Result;
-use semeion::Size;
+use anyhow::{anyhow, bail, Result};
+use semeion::{Distance, Location, Offset, Size};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::Path};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::{fmt, fs};
 
-use crate::entity::{phero, Kind};
+use super::map::Map;
+use crate::entity::{self, phero, Kind};
+
+/// The key of a configuration file that gives the path of the configuration
+/// file it extends, relative to its own directory.
+const EXTENDS: &str = "extends";
+
+/// The key of a configuration file that gives the path of the map file,
+/// relative to its own directory.
+const MAP: &str = "map";
+
+/// The color of the colony configured through `nest` and `ants`, when no
+/// `colonies` are given.
+const COLONY_COLOR: (u8, u8, u8) = (255, 0, 0);
+
+/// The format of a configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
 
 /// The game configuration.
+///
+/// All the fields are optional: the missing ones take the value of the file
+/// being extended, if any, or the default value otherwise.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Conf {
     pub fps: Option<u32>,
+    /// The seed of the simulation, randomly generated when not specified.
+    #[serde(default)]
     pub seed: Option<u64>,
+    /// The map file the environment is laid out from, if any: its size
+    /// replaces `env.dimension`, and its walls, Nests and Morsels are added to
+    /// the ones of the configuration.
+    pub map: Option<PathBuf>,
     pub env: Environment,
     pub nest: Nest,
     pub ants: Ants,
     pub morsels: Morsels,
     pub pheromones: Pheromones,
+    pub obstacles: Obstacles,
+    /// The colonies competing for the same food, each with its own Nest; if
+    /// empty there is a single colony, with the Nest and Ants configured in
+    /// `nest` and `ants`.
+    pub colonies: Vec<Colony>,
+    /// The conditions that end the simulation as soon as any of them is met.
+    pub end: Vec<EndCondition>,
 }
 
 impl Default for Conf {
@@ -23,22 +63,91 @@ impl Default for Conf {
         Self {
             fps: Some(24),
             seed: Some(0),
+            map: None,
             env: Environment::default(),
             nest: Nest::default(),
             ants: Ants::default(),
             morsels: Morsels::default(),
             pheromones: Pheromones::default(),
+            obstacles: Obstacles::default(),
+            colonies: Vec::new(),
+            end: vec![EndCondition::AllFood],
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A condition that ends the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub enum EndCondition {
+    /// All the food has been moved to the Nest.
+    AllFood,
+    /// At least the given percentage of the food has been moved to the Nest.
+    FoodPercentage(f64),
+    /// The given number of generations has been reached.
+    MaxGenerations(u64),
+    /// The simulation has been running for the given number of seconds.
+    WallClock(f64),
+    /// The simulation never ends on its own.
+    Never,
+}
+
+impl fmt::Display for EndCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::AllFood => write!(f, "all the food collected"),
+            Self::FoodPercentage(percentage) => {
+                write!(f, "{}% of the food collected", percentage)
+            }
+            Self::MaxGenerations(max) => {
+                write!(f, "{} generations reached", max)
+            }
+            Self::WallClock(secs) => write!(f, "{}s wall clock limit", secs),
+            Self::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// A problem found in the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The path of the field with the problem (e.g. `nest.location`).
+    pub path: String,
+    /// The description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Environment {
     pub dimension: (i32, i32),
     pub tile_side: f32,
     pub background: (u8, u8, u8),
     pub grid: Grid,
+    /// How the edges of the environment behave.
+    pub topology: Topology,
+}
+
+/// The topology of the environment, that is how its edges behave.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Topology {
+    /// The opposite edges are joined, so that whatever leaves the environment
+    /// from one edge enters it again from the opposite one.
+    #[default]
+    Torus,
+    /// The edges are bounds that bounce back whatever moves beyond them.
+    Reflective,
+    /// The edges are bounds that stop whatever moves beyond them.
+    Blocking,
 }
 
 impl Default for Environment {
@@ -47,16 +156,19 @@ impl Default for Environment {
             dimension: (30, 30),
             tile_side: 25.0,
             background: (25, 75, 75),
-            grid: Grid { visible: false },
+            grid: Grid::default(),
+            topology: Topology::default(),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Nest {
     pub visible: bool,
     pub location: (i32, i32),
+    /// How the Nests spawn new Ants from their food, if they do.
+    pub growth: Option<Growth>,
 }
 
 impl Default for Nest {
@@ -64,12 +176,33 @@ impl Default for Nest {
         Self {
             visible: true,
             location: (25, 25),
+            growth: None,
+        }
+    }
+}
+
+/// The growth of the colonies, whose Nests spawn a new Ant for each given
+/// amount of food stored, until the colony reaches its maximum population.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Growth {
+    /// The units of food a Nest takes from its storage to spawn an Ant.
+    pub food_per_ant: u64,
+    /// The maximum number of Ants of each colony.
+    pub max_population: usize,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Self {
+            food_per_ant: 10,
+            max_population: 100,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Ants {
     pub visible: bool,
     pub count: usize,
@@ -77,6 +210,19 @@ pub struct Ants {
     pub max_phero_concentration: u16,
     pub phero_decrease: u16,
     pub phero_increase_ratio: f64,
+    /// The distance, in number of tiles, within which the Ants sense their
+    /// surroundings.
+    pub perception_radius: usize,
+    /// The distance within which the Ants see the Nest and the Morsels, if
+    /// different from the perception radius.
+    pub sight_radius: Option<usize>,
+    /// The distance within which the Ants smell the pheromones, if different
+    /// from the perception radius.
+    pub smell_radius: Option<usize>,
+    /// How the Ants choose the tile to move to when following a trail.
+    pub transition: Transition,
+    /// The energy of the Ants, that live forever if not given.
+    pub energy: Option<Energy>,
 }
 
 impl Default for Ants {
@@ -88,16 +234,121 @@ impl Default for Ants {
             max_phero_concentration: 200,
             phero_decrease: 2,
             phero_increase_ratio: 0.1,
+            perception_radius: 1,
+            sight_radius: None,
+            smell_radius: None,
+            transition: Transition::default(),
+            energy: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Ants {
+    /// Gets the distance within which the Ants see the Nest and the Morsels.
+    pub fn sight(&self) -> usize {
+        self.sight_radius.unwrap_or(self.perception_radius)
+    }
+
+    /// Gets the distance within which the Ants smell the pheromones.
+    pub fn smell(&self) -> usize {
+        self.smell_radius.unwrap_or(self.perception_radius)
+    }
+}
+
+/// The energy of the Ants, that drains as they move and is refilled by eating
+/// a unit of food, either from their Nest or from a Morsel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Energy {
+    /// The energy of an Ant when born and after eating.
+    pub capacity: u64,
+    /// The energy spent by an Ant to move to an adjacent tile.
+    pub move_cost: u64,
+    /// The fraction of the capacity below which an Ant is hungry, and eats
+    /// when in its Nest or in a Morsel.
+    pub hunger: f64,
+}
+
+impl Default for Energy {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            move_cost: 1,
+            hunger: 0.5,
+        }
+    }
+}
+
+/// The rule the Ants follow to choose the next tile of a trail of pheromones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Transition {
+    /// The tile with the strongest scent is always chosen.
+    #[default]
+    Greedy,
+    /// The Ant System rule: each tile is chosen with a probability
+    /// proportional to τ^α·η^β, where τ is the concentration of its pheromone
+    /// and η the inverse of its distance, unless the Ant ignores the trail to
+    /// explore, with the given probability.
+    #[serde(rename_all = "camelCase")]
+    Probabilistic {
+        #[serde(default = "Transition::default_alpha")]
+        alpha: f64,
+        #[serde(default = "Transition::default_beta")]
+        beta: f64,
+        #[serde(default)]
+        exploration: f64,
+    },
+}
+
+impl Transition {
+    fn default_alpha() -> f64 {
+        1.0
+    }
+
+    fn default_beta() -> f64 {
+        2.0
+    }
+}
+
+/// A colony of Ants, with its own Nest and pheromones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct Colony {
+    /// The location of the Nest of the colony.
+    pub location: (i32, i32),
+    /// The number of Ants of the colony.
+    pub count: usize,
+    /// The color the Nest and the Ants of the colony are drawn with.
+    #[serde(default = "Colony::default_color")]
+    pub color: (u8, u8, u8),
+    /// The transition rule of the Ants of the colony, if different from the
+    /// one of `ants`.
+    #[serde(default)]
+    pub transition: Option<Transition>,
+}
+
+impl Colony {
+    fn default_color() -> (u8, u8, u8) {
+        COLONY_COLOR
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Morsels {
     pub visible: bool,
     pub count: usize,
     pub storage: u64,
+    /// How the `count` random Morsels are placed.
+    pub placement: Placement,
+    /// The Morsels placed at a given location, in addition to the `count`
+    /// ones placed at random.
+    pub placed: Vec<PlacedMorsel>,
+    /// How the Morsels regrow their food, if they do.
+    pub regrowth: Option<Regrowth>,
+    /// How new Morsels appear over time, if they do.
+    pub spawning: Option<Spawning>,
 }
 
 impl Default for Morsels {
@@ -106,40 +357,192 @@ impl Default for Morsels {
             visible: true,
             count: 20,
             storage: 30,
+            placement: Placement::default(),
+            placed: Vec::new(),
+            regrowth: None,
+            spawning: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The regrowth of the Morsels, which stay in the environment once emptied so
+/// that they can regrow from nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Regrowth {
+    /// The units of food each Morsel regrows at once.
+    pub amount: u64,
+    /// The number of generations between two regrowths.
+    pub interval: u64,
+    /// The storage the Morsels regrow up to, `morsels.storage` if not given.
+    pub cap: Option<u64>,
+}
+
+impl Default for Regrowth {
+    fn default() -> Self {
+        Self {
+            amount: 1,
+            interval: 10,
+            cap: None,
+        }
+    }
+}
+
+/// The appearance of new Morsels over time, each placed at random according
+/// to the placement constraints.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Spawning {
+    /// The probability a new Morsel appears at each generation, so that at
+    /// most one appears at the given rate in any generation.
+    pub rate: f64,
+    /// The generations a new Morsel appears at, besides the ones appearing
+    /// at the given rate.
+    pub schedule: Vec<u64>,
+    /// The food stored by the new Morsels, `morsels.storage` if not given.
+    pub storage: Option<u64>,
+}
+
+/// The placement of the random Morsels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Placement {
+    pub strategy: Strategy,
+    /// The minimum distance of each Morsel from every Nest, in tiles, at
+    /// least 1 so that no Morsel is placed on a Nest.
+    pub min_nest_distance: usize,
+    /// The minimum distance between any two Morsels, in tiles, at least 1 so
+    /// that no two Morsels are placed on the same tile.
+    pub min_distance: usize,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::default(),
+            min_nest_distance: 1,
+            min_distance: 1,
+        }
+    }
+}
+
+/// The strategy used to place the random Morsels.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Strategy {
+    /// Anywhere in the environment, with the same probability.
+    #[default]
+    Uniform,
+    /// In the given number of patches, each within the given radius from a
+    /// random center.
+    Clustered { patches: usize, radius: usize },
+    /// On a ring at the given distance from the Nest (or from each Nest, in
+    /// turn, when there are many colonies).
+    Ring { distance: usize },
+    /// Evenly spread, at least the given radius apart from each other.
+    PoissonDisk { radius: usize },
+}
+
+/// A Morsel placed at a given location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct PlacedMorsel {
+    pub location: (i32, i32),
+    pub storage: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Pheromones {
     pub colony: ColonyPhero,
     pub food: FoodPhero,
+    /// The pheromones initially placed in the environment, for example to lay
+    /// down an existing trail.
+    pub placed: Vec<PlacedPhero>,
 }
 
-impl Default for Pheromones {
+/// A pheromone placed at a given location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacedPhero {
+    pub scent: phero::Scent,
+    /// The colony that can smell the pheromone.
+    #[serde(default)]
+    pub colony: entity::Colony,
+    pub location: (i32, i32),
+    pub concentration: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Obstacles {
+    pub visible: bool,
+    /// The walls the Ants must walk around.
+    pub walls: Vec<Wall>,
+}
+
+impl Default for Obstacles {
     fn default() -> Self {
         Self {
-            colony: ColonyPhero { visible: false },
-            food: FoodPhero { visible: false },
+            visible: true,
+            walls: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A wall made of the tiles on the straight line between its two ends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct Wall {
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct ColonyPhero {
     pub visible: bool,
+    /// How the concentration of the pheromone evaporates over time.
+    pub evaporation: Evaporation,
+    /// The fraction of the concentration of the pheromone that spreads evenly
+    /// to the adjacent tiles at each generation.
+    pub diffusion: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct FoodPhero {
     pub visible: bool,
+    /// How the concentration of the pheromone evaporates over time.
+    pub evaporation: Evaporation,
+    /// The fraction of the concentration of the pheromone that spreads evenly
+    /// to the adjacent tiles at each generation.
+    pub diffusion: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The model of the evaporation of a pheromone, that loses at least a unit of
+/// concentration at each generation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub enum Evaporation {
+    /// The given number of units are lost at each generation.
+    Linear(u64),
+    /// The given fraction ρ of the concentration is lost at each generation.
+    Exponential(f64),
+    /// Half of the concentration is lost every given number of generations.
+    HalfLife(f64),
+}
+
+impl Default for Evaporation {
+    fn default() -> Self {
+        Self::Linear(1)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Grid {
     pub visible: bool,
 }
@@ -149,11 +552,451 @@ impl Conf {
     pub fn parse(config_path: impl AsRef<Path>) -> Result<Self> {
         let config_path = config_path.as_ref();
         log::info!("Parsing game configuration from {:?}", config_path);
-        let contents = fs::read_to_string(config_path)?;
-        let conf = serde_json::from_str(&contents)?;
+        let value = read(config_path, &mut Vec::new())?;
+        let conf = serde_json::from_value(value)?;
         Ok(conf)
     }
 
+    /// Writes the configuration to the file with the given path, as JSON,
+    /// TOML or YAML according to its extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        log::info!("Saving game configuration to {:?}", path);
+        let contents = match Format::from_path(path)? {
+            Format::Json => serde_json::to_string_pretty(self)?,
+            Format::Toml => {
+                if self.fps.is_none() {
+                    log::warn!("TOML cannot represent unlimited fps");
+                }
+                toml::to_string(self)?
+            }
+            // write the enum variants as maps, as in JSON, rather than tags
+            Format::Yaml => {
+                serde_yaml::to_string(&serde_json::to_value(self)?)?
+            }
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Sets the value of the field with the given path, made of the names of
+    /// the nested fields separated by dots (e.g. `ants.memorySpan`).
+    ///
+    /// The optional sections that are not given along the path take their
+    /// default value, so that any of their fields can be set (e.g.
+    /// `ants.energy.capacity`).
+    pub fn set(&mut self, field: &str, value: Value) -> Result<()> {
+        let unknown = || anyhow!("Unknown configuration field {}", field);
+        let keys: Vec<_> = field.split('.').collect();
+        let pointer = |depth: usize| -> String {
+            keys[..depth]
+                .iter()
+                .map(|key| format!("/{}", key))
+                .collect()
+        };
+
+        let mut conf = serde_json::to_value(&*self)?;
+        for depth in 1..keys.len() {
+            let section =
+                conf.pointer_mut(&pointer(depth)).ok_or_else(unknown)?;
+            if !section.is_null() {
+                continue;
+            }
+            // an empty section is filled with the default value of its fields
+            *section = Value::Object(Default::default());
+            let defaults = serde_json::from_value::<Self>(conf.clone())
+                .map_err(|_| unknown())
+                .and_then(|defaults| Ok(serde_json::to_value(defaults)?))?;
+            let section = defaults.pointer(&pointer(depth)).cloned();
+            *conf.pointer_mut(&pointer(depth)).ok_or_else(unknown)? =
+                section.ok_or_else(unknown)?;
+        }
+
+        *conf.pointer_mut(&pointer(keys.len())).ok_or_else(unknown)? = value;
+        *self = serde_json::from_value(conf)
+            .map_err(|e| anyhow!("Invalid value for {}: {}", field, e))?;
+        Ok(())
+    }
+
+    /// Applies the given assignment, made of the path of a field and its value
+    /// separated by `=` (e.g. `ants.count=50`). The value is parsed as JSON,
+    /// or taken as a string if it's not valid JSON.
+    pub fn assign(&mut self, assignment: &str) -> Result<()> {
+        let (field, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid assignment {}", assignment))?;
+        let value = serde_json::from_str(value)
+            .unwrap_or_else(|_| Value::String(value.to_string()));
+        self.set(field.trim(), value)
+    }
+
+    /// Checks that the configuration describes a valid simulation, returning
+    /// an error listing all the problems found, if any.
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let problems: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+        Err(anyhow!("Invalid configuration:\n{}", problems.join("\n")))
+    }
+
+    /// Gets all the problems of the configuration, each with the path of the
+    /// field it was found at.
+    pub fn problems(&self) -> Vec<Problem> {
+        if self.map.is_some() {
+            // the map must be laid out for the locations to be meaningful
+            return match self.with_map() {
+                Ok(conf) => conf.problems(),
+                Err(e) => vec![Problem {
+                    path: MAP.to_string(),
+                    message: e.to_string(),
+                }],
+            };
+        }
+
+        let mut problems = Vec::new();
+        let mut report = |path: &str, message: String| {
+            problems.push(Problem {
+                path: path.to_string(),
+                message,
+            })
+        };
+
+        if self.fps == Some(0) {
+            report("fps", "0 is not positive".into());
+        }
+
+        let (width, height) = self.env.dimension;
+        if width <= 0 || height <= 0 {
+            report(
+                "env.dimension",
+                format!("{:?} is not positive", self.env.dimension),
+            );
+        } else {
+            let mut check_location = |path: &str, location: (i32, i32)| {
+                let (x, y) = location;
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    report(
+                        path,
+                        format!(
+                            "{:?} is outside {}x{}",
+                            location, width, height
+                        ),
+                    );
+                }
+            };
+            if self.colonies.is_empty() {
+                check_location("nest.location", self.nest.location);
+            }
+            for (i, colony) in self.colonies.iter().enumerate() {
+                let path = format!("colonies[{}].location", i);
+                check_location(&path, colony.location);
+            }
+            for (i, morsel) in self.morsels.placed.iter().enumerate() {
+                let path = format!("morsels.placed[{}].location", i);
+                check_location(&path, morsel.location);
+            }
+            for (i, phero) in self.pheromones.placed.iter().enumerate() {
+                let path = format!("pheromones.placed[{}].location", i);
+                check_location(&path, phero.location);
+            }
+            for (i, wall) in self.obstacles.walls.iter().enumerate() {
+                check_location(
+                    &format!("obstacles.walls[{}].from", i),
+                    wall.from,
+                );
+                check_location(&format!("obstacles.walls[{}].to", i), wall.to);
+            }
+        }
+
+        // nothing can be placed on the obstacles
+        let obstacles = self.obstacles();
+        let mut check_obstacle = |path: &str, location: (i32, i32)| {
+            if obstacles.contains(&location) {
+                report(path, format!("{:?} is on an obstacle", location));
+            }
+        };
+        if self.colonies.is_empty() {
+            check_obstacle("nest.location", self.nest.location);
+        }
+        for (i, colony) in self.colonies.iter().enumerate() {
+            check_obstacle(
+                &format!("colonies[{}].location", i),
+                colony.location,
+            );
+        }
+        for (i, morsel) in self.morsels.placed.iter().enumerate() {
+            let path = format!("morsels.placed[{}].location", i);
+            check_obstacle(&path, morsel.location);
+        }
+        for (i, phero) in self.pheromones.placed.iter().enumerate() {
+            let path = format!("pheromones.placed[{}].location", i);
+            check_obstacle(&path, phero.location);
+        }
+
+        // also rejects NaN
+        if !(self.env.tile_side > 0.0 && self.env.tile_side.is_finite()) {
+            report(
+                "env.tileSide",
+                format!("{} is not positive", self.env.tile_side),
+            );
+        }
+
+        for scent in [phero::Scent::Colony, phero::Scent::Food] {
+            let name = format!("pheromones.{:?}", scent).to_lowercase();
+            match self.pheromones.evaporation(scent) {
+                Evaporation::Linear(0) => report(
+                    &format!("{}.evaporation.linear", name),
+                    "0 is not positive".into(),
+                ),
+                Evaporation::Exponential(rate)
+                    if !(rate > 0.0 && rate <= 1.0) =>
+                {
+                    report(
+                        &format!("{}.evaporation.exponential", name),
+                        format!("{} is not between 0 (excluded) and 1", rate),
+                    );
+                }
+                Evaporation::HalfLife(generations)
+                    if !(generations > 0.0 && generations.is_finite()) =>
+                {
+                    report(
+                        &format!("{}.evaporation.halfLife", name),
+                        format!("{} is not positive", generations),
+                    );
+                }
+                _ => (),
+            }
+            let diffusion = self.pheromones.diffusion(scent);
+            if !(0.0..1.0).contains(&diffusion) {
+                report(
+                    &format!("{}.diffusion", name),
+                    format!("{} is not between 0 and 1 (excluded)", diffusion),
+                );
+            }
+        }
+
+        let ratio = self.ants.phero_increase_ratio;
+        if !(0.0..=1.0).contains(&ratio) {
+            report(
+                "ants.pheroIncreaseRatio",
+                format!("{} is not between 0 and 1", ratio),
+            );
+        }
+
+        let radii = [
+            ("ants.perceptionRadius", Some(self.ants.perception_radius)),
+            ("ants.sightRadius", self.ants.sight_radius),
+            ("ants.smellRadius", self.ants.smell_radius),
+        ];
+        for (path, radius) in radii {
+            if radius == Some(0) {
+                report(path, "0 is not positive".into());
+            }
+        }
+        // the Ants see the tiles of their neighborhood, that must fit within
+        // the environment
+        let sight = self.ants.sight();
+        let side = sight.saturating_mul(2).saturating_add(1);
+        if width > 0 && height > 0 && side > width.min(height) as usize {
+            let path = match self.ants.sight_radius {
+                Some(_) => "ants.sightRadius",
+                None => "ants.perceptionRadius",
+            };
+            report(
+                path,
+                format!("{} is too large for {}x{}", sight, width, height),
+            );
+        }
+
+        if let Some(growth) = self.nest.growth {
+            if growth.food_per_ant == 0 {
+                report("nest.growth.foodPerAnt", "0 is not positive".into());
+            }
+        }
+
+        if let Some(energy) = self.ants.energy {
+            if energy.capacity == 0 {
+                report("ants.energy.capacity", "0 is not positive".into());
+            }
+            if !(0.0..=1.0).contains(&energy.hunger) {
+                report(
+                    "ants.energy.hunger",
+                    format!("{} is not between 0 and 1", energy.hunger),
+                );
+            }
+        }
+
+        let transitions =
+            self.colonies.iter().enumerate().map(|(i, colony)| {
+                (format!("colonies[{}].transition", i), colony.transition)
+            });
+        let transitions = std::iter::once((
+            "ants.transition".to_string(),
+            Some(self.ants.transition),
+        ))
+        .chain(transitions);
+        for (path, transition) in transitions {
+            if let Some(Transition::Probabilistic {
+                alpha,
+                beta,
+                exploration,
+            }) = transition
+            {
+                let path = format!("{}.probabilistic", path);
+                for (name, exponent) in [("alpha", alpha), ("beta", beta)] {
+                    if !(exponent >= 0.0 && exponent.is_finite()) {
+                        report(
+                            &format!("{}.{}", path, name),
+                            format!("{} is not 0 or positive", exponent),
+                        );
+                    }
+                }
+                if !(0.0..=1.0).contains(&exploration) {
+                    report(
+                        &format!("{}.exploration", path),
+                        format!("{} is not between 0 and 1", exploration),
+                    );
+                }
+            }
+        }
+
+        let count = self.morsels.count as u64;
+        match count.checked_mul(self.morsels.storage) {
+            None => report(
+                "morsels.storage",
+                format!(
+                    "{} morsels storing {} each overflow the total storage",
+                    count, self.morsels.storage
+                ),
+            ),
+            Some(total) => {
+                let mut placed = self.morsels.placed.iter().map(|m| m.storage);
+                if placed.try_fold(total, u64::checked_add).is_none() {
+                    report(
+                        "morsels.placed",
+                        "the total storage overflows".into(),
+                    );
+                }
+            }
+        }
+        let placement = &self.morsels.placement;
+        if placement.min_nest_distance == 0 {
+            report(
+                "morsels.placement.minNestDistance",
+                "0 is not positive".into(),
+            );
+        }
+        if placement.min_distance == 0 {
+            report("morsels.placement.minDistance", "0 is not positive".into());
+        }
+        match placement.strategy {
+            Strategy::Clustered { patches: 0, .. } => report(
+                "morsels.placement.strategy.clustered.patches",
+                "0 is not positive".into(),
+            ),
+            Strategy::PoissonDisk { radius: 0 } => report(
+                "morsels.placement.strategy.poissonDisk.radius",
+                "0 is not positive".into(),
+            ),
+            _ => (),
+        }
+        for (i, morsel) in self.morsels.placed.iter().enumerate() {
+            if morsel.storage == 0 {
+                report(
+                    &format!("morsels.placed[{}].storage", i),
+                    "0 is not positive".into(),
+                );
+            }
+        }
+
+        if let Some(regrowth) = self.morsels.regrowth {
+            if regrowth.interval == 0 {
+                report("morsels.regrowth.interval", "0 is not positive".into());
+            }
+        }
+        if let Some(spawning) = &self.morsels.spawning {
+            if !(0.0..=1.0).contains(&spawning.rate) {
+                report(
+                    "morsels.spawning.rate",
+                    format!("{} is not between 0 and 1", spawning.rate),
+                );
+            }
+            if spawning.storage == Some(0) {
+                report("morsels.spawning.storage", "0 is not positive".into());
+            }
+        }
+
+        let mut nests = HashSet::new();
+        for (i, colony) in self.colonies.iter().enumerate() {
+            // a tile holds at most one Nest
+            if !nests.insert(colony.location) {
+                report(
+                    &format!("colonies[{}].location", i),
+                    format!("{:?} already has a Nest", colony.location),
+                );
+            }
+        }
+
+        let mut pheros = HashSet::new();
+        let colonies = self.colonies().len();
+        for (i, phero) in self.pheromones.placed.iter().enumerate() {
+            if phero.colony >= colonies {
+                report(
+                    &format!("pheromones.placed[{}].colony", i),
+                    format!(
+                        "{} is not one of the {} colonies",
+                        phero.colony, colonies
+                    ),
+                );
+            }
+            if phero.concentration == 0 {
+                report(
+                    &format!("pheromones.placed[{}].concentration", i),
+                    "0 is not positive".into(),
+                );
+            }
+            // a tile holds at most one pheromone of each scent
+            if !pheros.insert((phero.scent, phero.colony, phero.location)) {
+                report(
+                    &format!("pheromones.placed[{}]", i),
+                    format!(
+                        "{:?} already has a {:?} pheromone",
+                        phero.location, phero.scent
+                    ),
+                );
+            }
+        }
+
+        if self.end.is_empty() {
+            report("end", "no end condition given".into());
+        }
+        for (i, condition) in self.end.iter().enumerate() {
+            match *condition {
+                EndCondition::FoodPercentage(percentage)
+                    if !(0.0..=100.0).contains(&percentage) =>
+                {
+                    report(
+                        &format!("end[{}].foodPercentage", i),
+                        format!("{} is not between 0 and 100", percentage),
+                    );
+                }
+                EndCondition::WallClock(secs)
+                    if !(secs >= 0.0 && secs.is_finite()) =>
+                {
+                    report(
+                        &format!("end[{}].wallClock", i),
+                        format!("{} is not a valid number of seconds", secs),
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        problems
+    }
+
     /// Gets the size of the environment in number of pixels.
     pub fn size(&self) -> Size {
         let width = self.env.dimension.0 as f32 * self.env.tile_side;
@@ -164,28 +1007,659 @@ impl Conf {
     /// Returns true only if the given kind should be drawn.
     pub fn is_visible(&self, kind: &Kind) -> bool {
         match kind {
-            Kind::Grid => self.env.grid.visible,
             Kind::Ant => self.ants.visible,
             Kind::Morsel => self.morsels.visible,
-            Kind::Nest => self.nest.visible,
-            Kind::Phero { scent } => match scent {
-                phero::Scent::Colony => self.pheromones.colony.visible,
-                phero::Scent::Food => self.pheromones.food.visible,
-            },
+            Kind::Nest { .. } => self.nest.visible,
+            Kind::Obstacle => self.obstacles.visible,
         }
     }
 
     /// Gets initial the number of entities of the given kind.
     pub fn count(&self, kind: Kind) -> usize {
         match kind {
-            Kind::Ant => self.ants.count,
-            Kind::Morsel => self.morsels.count,
-            _ => 0,
+            Kind::Ant => self.colonies().iter().map(|c| c.count).sum(),
+            Kind::Morsel => self.morsels.count + self.morsels.placed.len(),
+            Kind::Nest { .. } => 0,
+            Kind::Obstacle => self.obstacles().len(),
+        }
+    }
+
+    /// Gets all the colonies, which are the ones listed in `colonies`, or the
+    /// single colony configured through `nest` and `ants` if none is listed.
+    pub fn colonies(&self) -> Vec<Colony> {
+        if !self.colonies.is_empty() {
+            return self.colonies.clone();
+        }
+        vec![Colony {
+            location: self.nest.location,
+            count: self.ants.count,
+            color: COLONY_COLOR,
+            transition: None,
+        }]
+    }
+
+    /// Gets the transition rule of the Ants of the given colony.
+    pub fn transition(&self, colony: entity::Colony) -> Transition {
+        self.colonies
+            .get(colony)
+            .and_then(|colony| colony.transition)
+            .unwrap_or(self.ants.transition)
+    }
+
+    /// Gets the locations of all the tiles occupied by the obstacles.
+    pub fn obstacles(&self) -> BTreeSet<(i32, i32)> {
+        self.obstacles.walls.iter().flat_map(Wall::tiles).collect()
+    }
+
+    /// Gets the configuration with the map, if any, laid out: the environment
+    /// takes the size of the map, each of its walls becomes a single tile
+    /// wall, and its Morsels are placed. The Nests of the map are the ones of
+    /// the colonies, in order, which are added with the Ants configured in
+    /// `ants` if none is listed.
+    pub fn with_map(&self) -> Result<Self> {
+        let mut conf = self.clone();
+        let path = match conf.map.take() {
+            Some(path) => path,
+            None => return Ok(conf),
+        };
+        let map = Map::load(path, conf.morsels.storage)?;
+
+        conf.env.dimension = map.dimension;
+        conf.obstacles
+            .walls
+            .extend(map.walls.into_iter().map(|tile| Wall {
+                from: tile,
+                to: tile,
+            }));
+        conf.morsels.placed.extend(
+            map.morsels
+                .into_iter()
+                .map(|(location, storage)| PlacedMorsel { location, storage }),
+        );
+        match (map.nests.as_slice(), conf.colonies.len()) {
+            ([], _) => (),
+            (&[location], 0) => conf.nest.location = location,
+            (nests, 0) => {
+                conf.colonies = nests
+                    .iter()
+                    .map(|&location| Colony {
+                        location,
+                        count: conf.ants.count,
+                        color: COLONY_COLOR,
+                        transition: None,
+                    })
+                    .collect();
+            }
+            (nests, count) if nests.len() == count => {
+                for (colony, &location) in conf.colonies.iter_mut().zip(nests) {
+                    colony.location = location;
+                }
+            }
+            (nests, count) => bail!(
+                "the map has {} Nests, but there are {} colonies",
+                nests.len(),
+                count
+            ),
         }
+        Ok(conf)
+    }
+
+    /// Returns true only if none of the end conditions can ever be met, so
+    /// that the simulation runs until stopped.
+    pub fn is_endless(&self) -> bool {
+        self.end
+            .iter()
+            .all(|&condition| condition == EndCondition::Never)
     }
 
     /// Gets the total food initially located in the environment.
     pub fn total_storage(&self) -> u64 {
-        self.morsels.storage * self.morsels.count as u64
+        let placed: u64 = self.morsels.placed.iter().map(|m| m.storage).sum();
+        self.morsels.storage * self.morsels.count as u64 + placed
+    }
+}
+
+impl Wall {
+    /// Gets the locations of the tiles the wall is made of, from one end to
+    /// the other, as drawn by the Bresenham's line algorithm.
+    ///
+    /// The tiles are always adjacent along one of the axes, so that the Ants
+    /// cannot slip through the wall diagonally.
+    pub fn tiles(&self) -> Vec<(i32, i32)> {
+        let (mut x, mut y) = self.from;
+        let (to_x, to_y) = self.to;
+        let (dx, dy) = ((to_x - x).abs(), -(to_y - y).abs());
+        let (sx, sy) = ((to_x - x).signum(), (to_y - y).signum());
+        let mut error = dx + dy;
+
+        let mut tiles = vec![(x, y)];
+        while (x, y) != self.to {
+            let double = error * 2;
+            let (step_x, step_y) = (double >= dy, double <= dx);
+            if step_x && step_y {
+                // fill the corner of the diagonal step
+                tiles.push((x + sx, y));
+            }
+            if step_x {
+                error += dy;
+                x += sx;
+            }
+            if step_y {
+                error += dx;
+                y += sy;
+            }
+            tiles.push((x, y));
+        }
+        tiles
+    }
+}
+
+impl Pheromones {
+    /// Gets the evaporation model of the pheromones with the given scent.
+    pub fn evaporation(&self, scent: phero::Scent) -> Evaporation {
+        match scent {
+            phero::Scent::Colony => self.colony.evaporation,
+            phero::Scent::Food => self.food.evaporation,
+        }
+    }
+
+    /// Returns true only if the pheromones with the given scent should be
+    /// drawn.
+    pub fn is_visible(&self, scent: phero::Scent) -> bool {
+        match scent {
+            phero::Scent::Colony => self.colony.visible,
+            phero::Scent::Food => self.food.visible,
+        }
+    }
+
+    /// Gets the diffusion rate of the pheromones with the given scent.
+    pub fn diffusion(&self, scent: phero::Scent) -> f64 {
+        match scent {
+            phero::Scent::Colony => self.colony.diffusion,
+            phero::Scent::Food => self.food.diffusion,
+        }
+    }
+}
+
+impl Morsels {
+    /// Gets the storage of a Morsel with the given storage once it regrew in
+    /// the given generation, if it regrows at all.
+    pub fn regrow(&self, storage: u64, generation: u64) -> u64 {
+        match self.regrowth {
+            Some(regrowth)
+                if regrowth.interval > 0
+                    && generation.is_multiple_of(regrowth.interval) =>
+            {
+                let cap = regrowth.cap.unwrap_or(self.storage);
+                storage.max(storage.saturating_add(regrowth.amount).min(cap))
+            }
+            _ => storage,
+        }
+    }
+
+    /// Gets the food stored by the new Morsels appearing over time.
+    pub fn spawned_storage(&self) -> u64 {
+        self.spawning
+            .as_ref()
+            .and_then(|spawning| spawning.storage)
+            .unwrap_or(self.storage)
+    }
+}
+
+impl Evaporation {
+    /// Gets the concentration left after a generation from the given one.
+    pub fn evaporate(self, concentration: u64) -> u64 {
+        let rate = match self {
+            Self::Linear(units) => {
+                return concentration.saturating_sub(units.max(1))
+            }
+            Self::Exponential(rate) => rate,
+            Self::HalfLife(generations) => {
+                1.0 - 0.5f64.powf(generations.recip())
+            }
+        };
+        let loss = (concentration as f64 * rate).round() as u64;
+        concentration.saturating_sub(loss.max(1))
+    }
+}
+
+impl Topology {
+    /// Gets the offset actually travelled when moving by the given offset from
+    /// the given location, in an environment of the given dimension: beyond
+    /// the edges of a bounded environment the movement is reflected back or
+    /// stopped, while on a torus it's never constrained.
+    pub fn constrain(
+        self,
+        location: Location,
+        offset: Offset,
+        dimension: (i32, i32),
+    ) -> Offset {
+        let axis = |start: i32, delta: i32, length: i32| {
+            let end = start + delta;
+            match self {
+                Self::Torus => end,
+                Self::Reflective => {
+                    // the reflection over both edges is periodic
+                    let period = (2 * (length - 1)).max(1);
+                    let end = end.rem_euclid(period);
+                    end.min(period - end)
+                }
+                Self::Blocking => end.clamp(0, length - 1),
+            }
+        };
+        Offset {
+            x: axis(location.x, offset.x, dimension.0) - location.x,
+            y: axis(location.y, offset.y, dimension.1) - location.y,
+        }
+    }
+
+    /// Returns true only if the tile at the given offset from the given
+    /// location can be reached directly, without crossing the edges of a
+    /// bounded environment.
+    pub fn contains(
+        self,
+        location: Location,
+        offset: Offset,
+        dimension: (i32, i32),
+    ) -> bool {
+        self.constrain(location, offset, dimension) == offset
+    }
+
+    /// Gets the location reached when moving by the given offset from the
+    /// given location.
+    pub fn translate(
+        self,
+        location: Location,
+        offset: Offset,
+        dimension: (i32, i32),
+    ) -> Location {
+        let offset = self.constrain(location, offset, dimension);
+        *location.clone().translate(offset, dimension)
+    }
+
+    /// Gets the offset of a single tile from the given location towards the
+    /// given destination, along the shortest route, which on a torus may cross
+    /// the edges.
+    pub fn step(
+        self,
+        location: Location,
+        destination: Location,
+        dimension: (i32, i32),
+    ) -> Offset {
+        let delta = self.delta(location, destination, dimension);
+        Offset {
+            x: delta.x.signum(),
+            y: delta.y.signum(),
+        }
+    }
+
+    /// Gets the distance between the two given locations along the shortest
+    /// route, which on a torus may cross the edges.
+    pub fn distance(
+        self,
+        location: Location,
+        other: Location,
+        dimension: (i32, i32),
+        representation: Distance,
+    ) -> usize {
+        let delta = self.delta(location, other, dimension);
+        Location::origin().distance(
+            Location {
+                x: delta.x,
+                y: delta.y,
+            },
+            representation,
+        )
+    }
+
+    /// Gets the offset from the given location to the other one, along the
+    /// shortest route.
+    fn delta(
+        self,
+        location: Location,
+        other: Location,
+        dimension: (i32, i32),
+    ) -> Offset {
+        let axis = |start: i32, end: i32, length: i32| {
+            let delta = end - start;
+            if self != Self::Torus {
+                return delta;
+            }
+            let delta = delta.rem_euclid(length);
+            if delta > length / 2 {
+                delta - length
+            } else {
+                delta
+            }
+        };
+        Offset {
+            x: axis(location.x, other.x, dimension.0),
+            y: axis(location.y, other.y, dimension.1),
+        }
+    }
+}
+
+impl Format {
+    /// Gets the format according to the extension of the given path.
+    fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("toml") => Ok(Self::Toml),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            _ => Err(anyhow!("Unknown configuration format for {:?}", path)),
+        }
+    }
+
+    /// Parses the given contents into a generic value, which has the same
+    /// schema whatever the format.
+    fn parse(self, contents: &str) -> Result<Value> {
+        let value = match self {
+            Self::Json => serde_json::from_str(contents)?,
+            Self::Toml => toml::from_str(contents)?,
+            Self::Yaml => serde_yaml::from_str(contents)?,
+        };
+        Ok(value)
+    }
+}
+
+/// Reads the configuration file with the given path, merged over the file it
+/// extends, if any. The paths of the files already read are used to detect
+/// cycles.
+fn read(path: &Path, visited: &mut Vec<PathBuf>) -> Result<Value> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| anyhow!("Cannot read {:?}: {}", path, e))?;
+    if visited.contains(&canonical) {
+        bail!("{:?} extends itself", path);
+    }
+    visited.push(canonical);
+
+    let contents = fs::read_to_string(path)?;
+    let mut value = Format::from_path(path)?
+        .parse(&contents)
+        .map_err(|e| anyhow!("Cannot parse {:?}: {}", path, e))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    if let Some(Value::String(map)) =
+        value.as_object_mut().and_then(|v| v.get_mut(MAP))
+    {
+        *map = dir.join(&*map).to_string_lossy().into_owned();
+    }
+    let base = match value.as_object_mut().and_then(|v| v.remove(EXTENDS)) {
+        Some(Value::String(base)) => base,
+        Some(base) => bail!("{:?}: invalid {} {}", path, EXTENDS, base),
+        None => return Ok(value),
+    };
+
+    let base_path = dir.join(base);
+    log::info!("Extending game configuration {:?}", base_path);
+    let mut conf = read(&base_path, visited)?;
+    merge(&mut conf, value);
+    Ok(conf)
+}
+
+/// Merges the given value into the given base value: the fields of objects
+/// are merged recursively, while any other value replaces the base one.
+fn merge(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            for (key, value) in value {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the problems of the given configuration, as reported to the user.
+    fn problems(conf: &Conf) -> Vec<String> {
+        conf.problems().iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn default_conf() {
+        let conf = Conf::default();
+        assert!(conf.problems().is_empty());
+        assert!(conf.validate().is_ok());
+
+        let mut invalid = conf;
+        invalid.env.dimension = (0, 30);
+        assert_eq!(invalid.problems()[0].path, "env.dimension");
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn invalid_values() {
+        let mut conf = Conf::default();
+        conf.nest.location = (40, 10);
+        conf.env.tile_side = 0.0;
+        conf.ants.phero_increase_ratio = f64::NAN;
+        conf.morsels.storage = u64::MAX;
+        conf.end.push(EndCondition::FoodPercentage(120.0));
+        assert_eq!(
+            problems(&conf),
+            [
+                "nest.location: (40, 10) is outside 30x30",
+                "env.tileSide: 0 is not positive",
+                "ants.pheroIncreaseRatio: NaN is not between 0 and 1",
+                "morsels.storage: 20 morsels storing 18446744073709551615 \
+                 each overflow the total storage",
+                "end[1].foodPercentage: 120 is not between 0 and 100",
+            ]
+        );
+    }
+
+    #[test]
+    fn end_conditions() {
+        let mut conf = Conf::default();
+        conf.end.clear();
+        assert_eq!(problems(&conf), ["end: no end condition given"]);
+    }
+
+    #[test]
+    fn placed_entities() {
+        let mut conf = Conf::default();
+        conf.assign(
+            r#"morsels.placed=[
+                {"location": [5, 5], "storage": 10},
+                {"location": [30, 5], "storage": 10}
+            ]"#,
+        )
+        .unwrap();
+        conf.assign(
+            r#"pheromones.placed=[
+                {"scent": "food", "location": [12, 10], "concentration": 30},
+                {"scent": "food", "location": [12, 10], "concentration": 60}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            problems(&conf),
+            [
+                "morsels.placed[1].location: (30, 5) is outside 30x30",
+                "pheromones.placed[1]: (12, 10) already has a Food pheromone",
+            ]
+        );
+    }
+
+    #[test]
+    fn placement() {
+        let mut conf = Conf::default();
+        conf.morsels.placement.min_nest_distance = 0;
+        conf.morsels.placement.min_distance = 0;
+        assert_eq!(
+            problems(&conf),
+            [
+                "morsels.placement.minNestDistance: 0 is not positive",
+                "morsels.placement.minDistance: 0 is not positive",
+            ]
+        );
+    }
+
+    #[test]
+    fn colonies() {
+        let mut conf = Conf::default();
+        conf.assign(
+            r#"colonies=[
+                {"location": [5, 5], "count": 10},
+                {"location": [5, 5], "count": 10}
+            ]"#,
+        )
+        .unwrap();
+        conf.assign(
+            r#"pheromones.placed=[
+                {"scent": "food", "colony": 2, "location": [9, 9],
+                 "concentration": 10}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            problems(&conf),
+            [
+                "colonies[1].location: (5, 5) already has a Nest",
+                "pheromones.placed[0].colony: 2 is not one of the 2 colonies",
+            ]
+        );
+
+        // the location of the Nest configured through `nest` is ignored
+        conf.nest.location = (-1, -1);
+        conf.colonies[1].location = (20, 20);
+        conf.pheromones.placed.clear();
+        assert!(conf.problems().is_empty());
+    }
+
+    #[test]
+    fn obstacles() {
+        let mut conf = Conf::default();
+        conf.assign(r#"obstacles.walls=[{"from": [10, 5], "to": [10, 25]}]"#)
+            .unwrap();
+        assert!(conf.problems().is_empty());
+
+        conf.nest.location = (10, 20);
+        conf.morsels.placed = vec![PlacedMorsel {
+            location: (10, 8),
+            storage: 10,
+        }];
+        assert_eq!(
+            problems(&conf),
+            [
+                "nest.location: (10, 20) is on an obstacle",
+                "morsels.placed[0].location: (10, 8) is on an obstacle",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_map() {
+        let conf = Conf {
+            map: Some(PathBuf::from("missing-map.txt")),
+            ..Default::default()
+        };
+        let problems = conf.problems();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, "map");
+    }
+
+    #[test]
+    fn pheromones() {
+        let mut conf = Conf::default();
+        conf.assign(r#"pheromones.colony.evaporation={"exponential": 1.5}"#)
+            .unwrap();
+        conf.assign("pheromones.food.diffusion=1").unwrap();
+        assert_eq!(
+            problems(&conf),
+            [
+                "pheromones.colony.evaporation.exponential: 1.5 is not \
+                 between 0 (excluded) and 1",
+                "pheromones.food.diffusion: 1 is not between 0 and 1 \
+                 (excluded)",
+            ]
+        );
+    }
+
+    #[test]
+    fn perception_radius() {
+        let mut conf = Conf::default();
+        conf.assign("ants.perceptionRadius=0").unwrap();
+        conf.assign("ants.sightRadius=15").unwrap();
+        assert_eq!(
+            problems(&conf),
+            [
+                "ants.perceptionRadius: 0 is not positive",
+                "ants.sightRadius: 15 is too large for 30x30",
+            ]
+        );
+    }
+
+    #[test]
+    fn transition() {
+        let mut conf = Conf::default();
+        conf.assign(
+            r#"colonies=[
+                {"location": [5, 5], "count": 1},
+                {"location": [20, 20], "count": 1}
+            ]"#,
+        )
+        .unwrap();
+        conf.assign(
+            r#"ants.transition={"probabilistic": {"alpha": -1, "beta": 2}}"#,
+        )
+        .unwrap();
+        conf.colonies[1].transition = Some(Transition::Probabilistic {
+            alpha: 1.0,
+            beta: 2.0,
+            exploration: 2.0,
+        });
+        assert_eq!(
+            problems(&conf),
+            [
+                "ants.transition.probabilistic.alpha: -1 is not 0 or positive",
+                "colonies[1].transition.probabilistic.exploration: 2 is not \
+                 between 0 and 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn energy() {
+        let mut conf = Conf::default();
+        conf.assign(r#"ants.energy={"capacity": 0, "hunger": 2}"#)
+            .unwrap();
+        assert_eq!(
+            problems(&conf),
+            [
+                "ants.energy.capacity: 0 is not positive",
+                "ants.energy.hunger: 2 is not between 0 and 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn growth() {
+        let mut conf = Conf::default();
+        conf.assign(r#"nest.growth={"foodPerAnt": 0}"#).unwrap();
+        assert_eq!(
+            problems(&conf),
+            ["nest.growth.foodPerAnt: 0 is not positive"]
+        );
+    }
+
+    #[test]
+    fn food_dynamics() {
+        let mut conf = Conf::default();
+        conf.assign(r#"morsels.regrowth={"interval": 0}"#).unwrap();
+        conf.assign(r#"morsels.spawning={"rate": 2, "storage": 0}"#)
+            .unwrap();
+        assert_eq!(
+            problems(&conf),
+            [
+                "morsels.regrowth.interval: 0 is not positive",
+                "morsels.spawning.rate: 2 is not between 0 and 1",
+                "morsels.spawning.storage: 0 is not positive",
+            ]
+        );
     }
 }
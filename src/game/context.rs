@@ -1,11 +1,13 @@
-use anyhow::Result;
-use ggez::graphics;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use semeion::*;
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use super::conf::Conf;
-use crate::entity::{ant, grid, morsel, nest, phero, Kind};
+use super::field::{PheroField, Traces};
+use super::record::Event;
+use crate::entity::{phero, Colony};
 
 /// The game context.
 #[derive(Default)]
@@ -14,43 +16,39 @@ pub struct Context {
     pub conf: Conf,
     // The next available entity ID.
     id: AtomicUsize,
-    // The map of entities meshes depending on their kind.
-    meshes: HashMap<Kind, graphics::Mesh>,
+    // Whether the events of the simulation are being recorded.
+    recording: AtomicBool,
+    // The events recorded since the beginning of the current generation, each
+    // with the Ant that caused it, if any.
+    events: Mutex<Vec<(Option<entity::Id>, Event)>>,
+    // The pheromones left in the environment.
+    pheros: RwLock<PheroField>,
+    // The changes made to the pheromones during the current generation.
+    traces: Mutex<Traces>,
+    // The number of Ants of each colony that died of hunger.
+    deaths: Mutex<Vec<u64>>,
+    // The number of Ants of each colony spawned by its Nest.
+    births: Mutex<Vec<u64>>,
 }
 
 impl Context {
-    /// Constructs a new game context with no graphics context.
-    pub fn new(conf: Conf) -> Self {
+    /// Constructs a new game context.
+    ///
+    /// If the configuration doesn't specify any seed, a new one is randomly
+    /// generated and recorded in the configuration, so that the simulation
+    /// can be reproduced.
+    pub fn new(mut conf: Conf) -> Self {
+        let seed = *conf.seed.get_or_insert_with(rand::random);
+        log::info!("Using simulation seed {}", seed);
         Self {
+            pheros: RwLock::new(PheroField::new(&conf)),
+            deaths: Mutex::new(vec![0; conf.colonies().len()]),
+            births: Mutex::new(vec![0; conf.colonies().len()]),
             conf,
-            id: AtomicUsize::default(),
-            meshes: HashMap::default(),
+            ..Self::default()
         }
     }
 
-    /// Constructs a new game context with meshes for each entity kind.
-    pub fn with_context(conf: Conf, ctx: &mut ggez::Context) -> Result<Self> {
-        let mut context = Self::new(conf);
-        let conf = &context.conf;
-
-        // initialize the meshes map for each entity kind
-        context.meshes.insert(Kind::Grid, grid::mesh(ctx, conf)?);
-        context.meshes.insert(Kind::Nest, nest::mesh(ctx, conf)?);
-        context
-            .meshes
-            .insert(Kind::Morsel, morsel::mesh(ctx, conf)?);
-        context.meshes.insert(Kind::Ant, ant::mesh(ctx, conf)?);
-
-        let scents = [phero::Scent::Colony, phero::Scent::Food];
-        for &scent in &scents {
-            context
-                .meshes
-                .insert(Kind::Phero { scent }, phero::mesh(scent, ctx, conf)?);
-        }
-
-        Ok(context)
-    }
-
     /// Gets a new Entity unique ID.
     ///
     /// IDs are simply generated by picking the next value, this gives us
@@ -60,10 +58,197 @@ impl Context {
         self.id.fetch_add(1, Ordering::SeqCst)
     }
 
-    /// Gets the graphics mesh associated with the given entity kind.
-    pub fn kind_mesh(&self, kind: &Kind) -> &graphics::Mesh {
-        self.meshes
-            .get(kind)
-            .unwrap_or_else(|| panic!("Mesh not found for {:?}", kind))
+    /// Gets the ID that will be assigned to the next Entity.
+    pub fn next_id(&self) -> entity::Id {
+        self.id.load(Ordering::SeqCst)
+    }
+
+    /// Sets the ID that will be assigned to the next Entity.
+    pub(crate) fn set_next_id(&mut self, id: entity::Id) {
+        *self.id.get_mut() = id;
+    }
+
+    /// Gets the number of Ants of each colony that died of hunger.
+    pub fn deaths(&self) -> Vec<u64> {
+        self.deaths.lock().expect("Poisoned deaths").clone()
+    }
+
+    /// Sets the number of Ants of each colony that died of hunger.
+    pub(crate) fn set_deaths(&mut self, deaths: Vec<u64>) {
+        let current = self.deaths.get_mut().expect("Poisoned deaths");
+        for (current, deaths) in current.iter_mut().zip(deaths) {
+            *current = deaths;
+        }
+    }
+
+    /// Records the death of the given Ant of the given colony.
+    pub(crate) fn record_death(&self, colony: Colony, ant: entity::Id) {
+        let mut deaths = self.deaths.lock().expect("Poisoned deaths");
+        if let Some(deaths) = deaths.get_mut(colony) {
+            *deaths += 1;
+        }
+        self.record(Event::Death { ant });
+    }
+
+    /// Gets the number of Ants of each colony spawned by its Nest.
+    pub fn births(&self) -> Vec<u64> {
+        self.births.lock().expect("Poisoned births").clone()
+    }
+
+    /// Sets the number of Ants of each colony spawned by its Nest.
+    pub(crate) fn set_births(&mut self, births: Vec<u64>) {
+        let current = self.births.get_mut().expect("Poisoned births");
+        for (current, births) in current.iter_mut().zip(births) {
+            *current = births;
+        }
+    }
+
+    /// Records the birth of the given Ant of the given colony, spawned by its
+    /// Nest at the given location.
+    pub(crate) fn record_birth(
+        &self,
+        colony: Colony,
+        ant: entity::Id,
+        location: Location,
+    ) {
+        let mut births = self.births.lock().expect("Poisoned births");
+        if let Some(births) = births.get_mut(colony) {
+            *births += 1;
+        }
+        self.record(Event::Birth {
+            ant,
+            colony,
+            location,
+        });
+    }
+
+    /// Gets the number of living Ants of the given colony, that is the Ants
+    /// it started with plus the ones born, minus the ones that died.
+    pub fn population(&self, colony: Colony) -> usize {
+        let count = self.conf.colonies().get(colony).map_or(0, |c| c.count);
+        let births = self.births().get(colony).copied().unwrap_or_default();
+        let deaths = self.deaths().get(colony).copied().unwrap_or_default();
+        (count as u64).saturating_add(births).saturating_sub(deaths) as usize
+    }
+
+    /// Gets the seed all the random decisions of the simulation derive from.
+    pub fn seed(&self) -> u64 {
+        self.conf.seed.unwrap_or_default()
+    }
+
+    /// Gets a new random number generator for the given stream.
+    ///
+    /// All the generators are seeded with the simulation seed, but each stream
+    /// produces an independent sequence: by giving each Entity its own stream
+    /// the outcome doesn't depend on the order the entities are scheduled in.
+    pub fn rng(&self, stream: u64) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed());
+        rng.set_stream(stream);
+        rng
+    }
+
+    /// Records the given event, if the simulation is being recorded.
+    ///
+    /// The Ants may record their events in any order, since they react
+    /// concurrently, so the events are taken sorted by the Ant that caused
+    /// them (see `Context::take_events`).
+    pub fn record(&self, event: Event) {
+        self.record_by(event.ant(), event);
+    }
+
+    /// Records the given event caused by the given Ant, if any, if the
+    /// simulation is being recorded.
+    fn record_by(&self, ant: Option<entity::Id>, event: Event) {
+        if self.recording.load(Ordering::Relaxed) {
+            self.events
+                .lock()
+                .expect("Poisoned events")
+                .push((ant, event));
+        }
+    }
+
+    /// Gets the pheromones left in the environment, as they were at the end of
+    /// the previous generation.
+    pub fn pheros(&self) -> RwLockReadGuard<'_, PheroField> {
+        self.pheros.read().expect("Poisoned pheromones")
+    }
+
+    /// Gets the pheromones left in the environment, to change them between
+    /// two generations.
+    pub(crate) fn pheros_mut(&self) -> RwLockWriteGuard<'_, PheroField> {
+        self.pheros.write().expect("Poisoned pheromones")
+    }
+
+    /// Deposits, on behalf of the given Ant, the given concentration of the
+    /// pheromone with the given scent of the given colony at the given
+    /// location, which is added to the pheromones at the end of the
+    /// generation.
+    pub fn deposit(
+        &self,
+        ant: entity::Id,
+        scent: phero::Scent,
+        colony: Colony,
+        location: Location,
+        concentration: u64,
+    ) {
+        let key = (scent, colony, location);
+        let mut traces = self.traces.lock().expect("Poisoned traces");
+        traces.deposits.push((key, concentration));
+        self.record_by(
+            Some(ant),
+            Event::Deposit {
+                scent,
+                colony,
+                location,
+                concentration,
+            },
+        );
+    }
+
+    /// Suppresses, on behalf of the given Ant, the pheromone with the given
+    /// scent of the given colony at the given location, which is cleared at
+    /// the end of the generation.
+    pub fn suppress(
+        &self,
+        ant: entity::Id,
+        scent: phero::Scent,
+        colony: Colony,
+        location: Location,
+    ) {
+        let key = (scent, colony, location);
+        let mut traces = self.traces.lock().expect("Poisoned traces");
+        traces.suppressed.push(key);
+        self.record_by(
+            Some(ant),
+            Event::Suppress {
+                scent,
+                colony,
+                location,
+            },
+        );
+    }
+
+    /// Applies the changes made to the pheromones during the generation that
+    /// is over, and moves them to the next generation.
+    pub(crate) fn update_pheros(&self) {
+        let traces =
+            std::mem::take(&mut *self.traces.lock().expect("Poisoned traces"));
+        self.pheros_mut().update(&self.conf, &traces);
+    }
+
+    /// Starts or stops recording the events of the simulation.
+    pub(crate) fn set_recording(&self, recording: bool) {
+        self.recording.store(recording, Ordering::Relaxed);
+    }
+
+    /// Takes all the events recorded so far: the ones of the environment
+    /// first, followed by the ones of each Ant in order of ID, so that they
+    /// don't depend on the order the Ants were scheduled in. The events of
+    /// the same Ant keep the order they were recorded in.
+    pub(crate) fn take_events(&self) -> Vec<Event> {
+        let mut events =
+            std::mem::take(&mut *self.events.lock().expect("Poisoned events"));
+        events.sort_by_key(|&(ant, _)| ant);
+        events.into_iter().map(|(_, event)| event).collect()
     }
 }
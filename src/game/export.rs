@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// The format tabular data, such as sweep outcomes and metrics, is exported
+/// in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Comma separated values, with a header row.
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+impl Format {
+    /// Gets the format according to the extension of the given path.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Ok(Self::Csv),
+            Some("json" | "jsonl") => Ok(Self::JsonLines),
+            _ => Err(anyhow!("Unknown output format for {:?}", path)),
+        }
+    }
+}
+
+/// Writes tabular data, one row at a time.
+pub struct Writer {
+    format: Format,
+    out: Box<dyn Write>,
+    /// Whether the CSV header has been written already.
+    header: bool,
+}
+
+impl Writer {
+    /// Constructs a new Writer to the given output.
+    pub fn new(out: Box<dyn Write>, format: Format) -> Self {
+        Self {
+            format,
+            out,
+            header: false,
+        }
+    }
+
+    /// Creates a new file with the given path, which format depends on its
+    /// extension.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)?;
+        log::info!("Exporting to {:?}", path);
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Self::new(Box::new(file), format))
+    }
+
+    /// Constructs a new Writer to the standard output.
+    pub fn stdout(format: Format) -> Self {
+        Self::new(Box::new(io::stdout()), format)
+    }
+
+    /// Writes a row made of the given columns, which names and order must be
+    /// the same for all the rows.
+    pub fn write(&mut self, columns: Vec<(String, Value)>) -> Result<()> {
+        match self.format {
+            Format::Csv => {
+                let (header, values): (Vec<_>, Vec<_>) = columns
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Value::String(value) => csv_field(&value),
+                            value => csv_field(&value.to_string()),
+                        };
+                        (csv_field(&name), value)
+                    })
+                    .unzip();
+                if !self.header {
+                    writeln!(self.out, "{}", header.join(","))?;
+                    self.header = true;
+                }
+                writeln!(self.out, "{}", values.join(","))?;
+            }
+            Format::JsonLines => {
+                // build the object by hand to preserve the columns order
+                let fields: Vec<_> = columns
+                    .into_iter()
+                    .map(|(name, value)| {
+                        format!("{}:{}", Value::from(name), value)
+                    })
+                    .collect();
+                writeln!(self.out, "{{{}}}", fields.join(","))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes all the rows written so far.
+    pub fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Quotes the given CSV field if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,190 @@
+use semeion::{Location, Offset};
+
+use super::Conf;
+use crate::entity::{self, phero};
+
+/// All the scents, in the order of the layers of each colony.
+const SCENTS: [phero::Scent; 2] = [phero::Scent::Colony, phero::Scent::Food];
+
+/// The scent, colony and location of a pheromone.
+pub type Key = (phero::Scent, entity::Colony, Location);
+
+/// The changes made to the pheromones by the Ants during a generation, which
+/// only take effect once the generation is over.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Traces {
+    /// The concentration of pheromone deposited on each tile.
+    pub deposits: Vec<(Key, u64)>,
+    /// The pheromones suppressed by the Ants.
+    pub suppressed: Vec<Key>,
+}
+
+/// The concentration of the pheromones of each scent and colony in each tile
+/// of the environment, stored as dense grids.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PheroField {
+    dimension: (i32, i32),
+    /// A grid for each scent of each colony, with the concentration of the
+    /// pheromone in each tile, row by row.
+    layers: Vec<Vec<u64>>,
+    /// Whether each tile is an obstacle, which the pheromones cannot spread
+    /// to.
+    obstacles: Vec<bool>,
+}
+
+impl PheroField {
+    /// Constructs a new field with no pheromones, for the environment and the
+    /// colonies of the given configuration.
+    pub fn new(conf: &Conf) -> Self {
+        let (width, height) = conf.env.dimension;
+        let tiles = (width.max(0) * height.max(0)) as usize;
+        let layers = vec![vec![0; tiles]; SCENTS.len() * conf.colonies().len()];
+        let mut field = Self {
+            dimension: conf.env.dimension,
+            layers,
+            obstacles: vec![false; tiles],
+        };
+        for location in conf.obstacles() {
+            if let Some(i) = field.index(location.into()) {
+                field.obstacles[i] = true;
+            }
+        }
+        field
+    }
+
+    /// Gets the concentration of the pheromone with the given scent, left by
+    /// the given colony at the given location.
+    pub fn get(
+        &self,
+        scent: phero::Scent,
+        colony: entity::Colony,
+        location: impl Into<Location>,
+    ) -> u64 {
+        let layer = self.layers.get(layer(scent, colony));
+        match (layer, self.index(location.into())) {
+            (Some(layer), Some(i)) => layer[i],
+            _ => 0,
+        }
+    }
+
+    /// Sets the concentration of the pheromone with the given scent, left by
+    /// the given colony at the given location.
+    pub fn set(
+        &mut self,
+        scent: phero::Scent,
+        colony: entity::Colony,
+        location: impl Into<Location>,
+        concentration: u64,
+    ) {
+        let i = self.index(location.into());
+        let layer = self.layers.get_mut(layer(scent, colony));
+        if let (Some(layer), Some(i)) = (layer, i) {
+            layer[i] = concentration;
+        }
+    }
+
+    /// Gets all the pheromones with a concentration greater than 0.
+    pub fn pheros(&self) -> impl Iterator<Item = (Key, u64)> + '_ {
+        let width = self.dimension.0;
+        self.layers.iter().enumerate().flat_map(move |(l, layer)| {
+            let scent = SCENTS[l % SCENTS.len()];
+            let colony = l / SCENTS.len();
+            layer.iter().enumerate().filter(|(_, &c)| c > 0).map(
+                move |(i, &concentration)| {
+                    let location = Location {
+                        x: i as i32 % width,
+                        y: i as i32 / width,
+                    };
+                    ((scent, colony, location), concentration)
+                },
+            )
+        })
+    }
+
+    /// Moves the pheromones to the next generation: all the pheromones
+    /// evaporate according to the model of their scent, the suppressed ones
+    /// are cleared, the deposited ones are added, and finally a fraction of
+    /// each pheromone spreads to the adjacent tiles according to the
+    /// diffusion rate of its scent.
+    pub fn update(&mut self, conf: &Conf, traces: &Traces) {
+        for (l, layer) in self.layers.iter_mut().enumerate() {
+            let scent = SCENTS[l % SCENTS.len()];
+            let evaporation = conf.pheromones.evaporation(scent);
+            for concentration in layer.iter_mut().filter(|c| **c > 0) {
+                *concentration = evaporation.evaporate(*concentration);
+            }
+        }
+
+        for &(scent, colony, location) in &traces.suppressed {
+            self.set(scent, colony, location, 0);
+        }
+        for &((scent, colony, location), deposit) in &traces.deposits {
+            let concentration = self.get(scent, colony, location);
+            let concentration = concentration.saturating_add(deposit);
+            self.set(scent, colony, location, concentration);
+        }
+
+        for l in 0..self.layers.len() {
+            let diffusion = conf.pheromones.diffusion(SCENTS[l % SCENTS.len()]);
+            if diffusion > 0.0 {
+                self.diffuse(conf, l, diffusion);
+            }
+        }
+    }
+
+    /// Spreads the given fraction of the concentration of each pheromone of
+    /// the given layer evenly to the adjacent tiles.
+    ///
+    /// The share of each adjacent tile is rounded down, so that a weak
+    /// pheromone doesn't spread at all, and the share of the tiles that are
+    /// obstacles or beyond the edges of a bounded environment is kept by the
+    /// pheromone itself.
+    fn diffuse(&mut self, conf: &Conf, layer: usize, diffusion: f64) {
+        let env = &conf.env;
+        let width = self.dimension.0;
+        let neighbors = Offset::border(1);
+
+        let mut diffused = self.layers[layer].clone();
+        for (i, &concentration) in self.layers[layer].iter().enumerate() {
+            let share = (concentration as f64 * diffusion) as u64 / 8;
+            if share == 0 {
+                continue;
+            }
+
+            let location = Location {
+                x: i as i32 % width,
+                y: i as i32 / width,
+            };
+            for &offset in &neighbors {
+                if !env.topology.contains(location, offset, env.dimension) {
+                    continue;
+                }
+                let neighbor =
+                    env.topology.translate(location, offset, env.dimension);
+                match self.index(neighbor) {
+                    Some(n) if !self.obstacles[n] => {
+                        diffused[n] += share;
+                        diffused[i] -= share;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.layers[layer] = diffused;
+    }
+
+    /// Gets the index of the tile with the given location, if within the
+    /// environment.
+    fn index(&self, location: Location) -> Option<usize> {
+        let (width, height) = self.dimension;
+        let Location { x, y } = location;
+        (x >= 0 && y >= 0 && x < width && y < height)
+            .then(|| (y * width + x) as usize)
+    }
+}
+
+/// Gets the index of the layer of the given scent and colony.
+fn layer(scent: phero::Scent, colony: entity::Colony) -> usize {
+    let scent = SCENTS.iter().position(|&s| s == scent).unwrap_or_default();
+    colony * SCENTS.len() + scent
+}
@@ -0,0 +1,67 @@
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use super::{ColonyStats, EndCondition, State};
+
+/// The summary of a simulation run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    /// The seed the simulation was run with.
+    pub seed: u64,
+    /// The number of generations simulated.
+    pub generations: u64,
+    /// The amount of food collected in the Nests.
+    pub collected: u64,
+    /// The statistics of each colony.
+    pub colonies: Vec<ColonyStats>,
+    /// The total amount of food located in the environment.
+    pub total: u64,
+    /// Whether all the food was collected.
+    pub completed: bool,
+    /// The end condition that was met, if the simulation is over.
+    pub end: Option<EndCondition>,
+    /// The wall time of the run in seconds.
+    pub wall_time: f64,
+}
+
+impl Summary {
+    /// Summarizes the simulation with the given state, as of its current
+    /// generation.
+    pub fn new(state: &State) -> Self {
+        Self {
+            seed: state.context().seed(),
+            generations: state.generation(),
+            collected: state.delivered(),
+            colonies: state.colonies(),
+            total: state.total_storage(),
+            completed: state.is_food_collected(),
+            end: state.end_condition(),
+            wall_time: state.elapsed().as_secs_f64(),
+        }
+    }
+}
+
+/// Runs the simulation without any graphics, as fast as possible, until any
+/// of the end conditions of its configuration is met, refusing to start if
+/// none can ever be met.
+pub fn run(state: &mut State) -> Result<Summary> {
+    if state.context().conf.is_endless() {
+        bail!(
+            "The simulation would never end: an end condition other than \
+            \"never\", or a maximum number of generations, is required"
+        );
+    }
+    log::info!("Running headless simulation..");
+    while !state.is_simulation_over() {
+        state.nextgen()?;
+    }
+
+    let summary = Summary::new(state);
+    log::info!(
+        "Simulation over after {} generations: {}",
+        summary.generations,
+        summary.end.map(|end| end.to_string()).unwrap_or_default()
+    );
+    Ok(summary)
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, bail, Result};
+use std::fs::{self, File};
+use std::path::Path;
+
+/// The color of the pixels of a PNG map that are walls.
+const WALL_COLOR: (u8, u8, u8) = (0, 0, 0);
+
+/// The color of the pixels of a PNG map that are Nests.
+const NEST_COLOR: (u8, u8, u8) = (255, 0, 0);
+
+/// The color of the pixels of a PNG map that are empty.
+const EMPTY_COLOR: (u8, u8, u8) = (255, 255, 255);
+
+/// The layout of an environment read from a map file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Map {
+    /// The width and height of the map, in number of tiles.
+    pub dimension: (i32, i32),
+    /// The locations of the tiles that are walls.
+    pub walls: Vec<(i32, i32)>,
+    /// The locations of the Nests, in reading order (left to right, top to
+    /// bottom).
+    pub nests: Vec<(i32, i32)>,
+    /// The location and storage of each Morsel.
+    pub morsels: Vec<((i32, i32), u64)>,
+}
+
+impl Map {
+    /// Reads the map file with the given path, as a PNG image if its extension
+    /// is `.png`, or as ASCII text otherwise. The given storage is the one of
+    /// the Morsels of an ASCII map that don't specify it.
+    pub fn load(path: impl AsRef<Path>, storage: u64) -> Result<Self> {
+        let path = path.as_ref();
+        log::info!("Loading map from {:?}", path);
+        let map = match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => File::open(path)
+                .map_err(Into::into)
+                .and_then(Self::read_png),
+            _ => fs::read_to_string(path)
+                .map_err(Into::into)
+                .and_then(|contents| Self::parse_ascii(&contents, storage)),
+        };
+        map.map_err(|e| anyhow!("Cannot load map {:?}: {}", path, e))
+    }
+
+    /// Parses an ASCII map, where each line is a row of tiles: `#` is a wall,
+    /// `N` a Nest, `F` a Morsel with the given storage, a digit from 1 to 9 a
+    /// Morsel storing that many times the given storage, and `.` or a space an
+    /// empty tile.
+    pub fn parse_ascii(contents: &str, storage: u64) -> Result<Self> {
+        let mut map = Self::default();
+        let lines: Vec<_> = contents.trim_end().lines().collect();
+        for (y, line) in lines.iter().enumerate() {
+            for (x, tile) in line.trim_end().chars().enumerate() {
+                let location = (x as i32, y as i32);
+                match tile {
+                    '#' => map.walls.push(location),
+                    'N' => map.nests.push(location),
+                    'F' => map.morsels.push((location, storage)),
+                    '1'..='9' => {
+                        let times = tile.to_digit(10).unwrap_or_default();
+                        map.morsels.push((
+                            location,
+                            storage.saturating_mul(times.into()),
+                        ));
+                    }
+                    '.' | ' ' => (),
+                    _ => bail!(
+                        "unknown tile {:?} at line {}, column {}",
+                        tile,
+                        y + 1,
+                        x + 1
+                    ),
+                }
+            }
+        }
+
+        let width = lines.iter().map(|l| l.trim_end().chars().count()).max();
+        map.dimension = (width.unwrap_or_default() as i32, lines.len() as i32);
+        Ok(map)
+    }
+
+    /// Reads a PNG map, where each pixel is a tile: black is a wall, red a
+    /// Nest, any shade of green (with no red or blue) a Morsel storing as much
+    /// food as the value of its green channel, and white or transparent an
+    /// empty tile. Indexed and low bit depth images are expanded, and 16 bit
+    /// images stripped, to 8 bits per channel.
+    pub fn read_png(reader: impl std::io::Read) -> Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(
+            png::Transformations::EXPAND | png::Transformations::STRIP_16,
+        );
+        let (info, mut reader) = decoder.read_info()?;
+        if info.bit_depth != png::BitDepth::Eight {
+            bail!("unsupported bit depth {:?}", info.bit_depth);
+        }
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels)?;
+
+        let samples = info.color_type.samples();
+        let mut map = Self {
+            dimension: (info.width as i32, info.height as i32),
+            ..Self::default()
+        };
+        for (y, row) in pixels.chunks(info.line_size).enumerate() {
+            let row = &row[..info.width as usize * samples];
+            for (x, pixel) in row.chunks(samples).enumerate() {
+                let location = (x as i32, y as i32);
+                let (color, alpha) = match *pixel {
+                    [gray] => ((gray, gray, gray), u8::MAX),
+                    [gray, alpha] => ((gray, gray, gray), alpha),
+                    [r, g, b] => ((r, g, b), u8::MAX),
+                    [r, g, b, alpha] => ((r, g, b), alpha),
+                    _ => bail!("unsupported color type {:?}", info.color_type),
+                };
+                match color {
+                    _ if alpha == 0 => (),
+                    WALL_COLOR => map.walls.push(location),
+                    NEST_COLOR => map.nests.push(location),
+                    EMPTY_COLOR => (),
+                    (0, storage, 0) => {
+                        map.morsels.push((location, storage.into()))
+                    }
+                    _ => bail!("unknown color {:?} at {:?}", color, location),
+                }
+            }
+        }
+        Ok(map)
+    }
+}
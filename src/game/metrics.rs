@@ -0,0 +1,216 @@
+use anyhow::Result;
+use semeion::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{export, State};
+use crate::entity::{self, ant, morsel, phero, Kind};
+
+/// The number and total strength of the pheromones with the same scent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PheroStats {
+    /// The number of tiles with the pheromone.
+    pub count: usize,
+    /// The sum of the concentration of the pheromone in all the tiles.
+    pub strength: u64,
+}
+
+/// The food delivered and the number of Ants of a single colony.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ColonyStats {
+    /// The amount of food delivered to the Nest of the colony so far.
+    pub delivered: u64,
+    /// The number of Ants of the colony.
+    pub ants: usize,
+    /// The number of Ants of the colony that died of hunger so far.
+    pub deaths: u64,
+    /// The number of Ants of the colony spawned by its Nest so far.
+    pub births: u64,
+}
+
+/// The metrics of the simulation at a given generation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sample {
+    /// The generation the sample was taken at.
+    pub generation: u64,
+    /// The amount of food delivered to the Nests so far.
+    pub delivered: u64,
+    /// The statistics of each colony.
+    pub colonies: Vec<ColonyStats>,
+    /// The number of Ants searching for food.
+    pub foraging: usize,
+    /// The number of Ants carrying food back to the Nest.
+    pub carrying: usize,
+    /// The number of Ants that cannot release more pheromone and cannot find
+    /// any trail to follow.
+    pub lost: usize,
+    /// The number of Ants that died of hunger so far.
+    pub deaths: u64,
+    /// The number of Ants spawned by the Nests so far.
+    pub births: u64,
+    /// The pheromones that lead to the Nest.
+    pub colony_pheros: PheroStats,
+    /// The pheromones that lead to the food.
+    pub food_pheros: PheroStats,
+    /// The remaining storage of each Morsel by ID.
+    pub morsels: BTreeMap<Id, u64>,
+    /// The Morsels discovered in this generation, that is the Morsels the
+    /// Ants took food from for the first time.
+    pub discovered: Vec<Id>,
+}
+
+/// The collector of the metrics of a running simulation, that samples each
+/// generation.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    samples: Vec<Sample>,
+    /// The generation each Morsel was discovered at.
+    discoveries: BTreeMap<Id, u64>,
+}
+
+impl Sample {
+    /// Takes a sample of the current state of the simulation, that follows
+    /// the given previous sample, if any.
+    pub fn new(state: &State, previous: Option<&Sample>) -> Self {
+        let colonies = state.colonies();
+        let mut sample = Self {
+            generation: state.generation(),
+            delivered: colonies.iter().map(|c| c.delivered).sum(),
+            deaths: colonies.iter().map(|c| c.deaths).sum(),
+            births: colonies.iter().map(|c| c.births).sum(),
+            colonies,
+            foraging: 0,
+            carrying: 0,
+            lost: 0,
+            colony_pheros: PheroStats::default(),
+            food_pheros: PheroStats::default(),
+            morsels: BTreeMap::new(),
+            discovered: Vec::new(),
+        };
+
+        // the Morsels with no food left are removed from the environment
+        if let Some(previous) = previous {
+            sample.morsels =
+                previous.morsels.keys().map(|&id| (id, 0)).collect();
+        }
+
+        let pheros = state.context.pheros();
+        for ((scent, ..), concentration) in pheros.pheros() {
+            let stats = match scent {
+                phero::Scent::Colony => &mut sample.colony_pheros,
+                phero::Scent::Food => &mut sample.food_pheros,
+            };
+            stats.count += 1;
+            stats.strength = stats.strength.saturating_add(concentration);
+        }
+
+        for e in state.env.entities() {
+            match e.kind() {
+                Kind::Ant => {
+                    let ant: &ant::State = entity::downcast_state(e);
+                    match ant.activity() {
+                        ant::Activity::Foraging => sample.foraging += 1,
+                        ant::Activity::Carrying => sample.carrying += 1,
+                    }
+                    let location = e.location().expect("Invalid Ant location");
+                    if ant.is_lost(&pheros, location) {
+                        sample.lost += 1;
+                    }
+                }
+                Kind::Morsel => {
+                    let morsel: &morsel::State = entity::downcast_state(e);
+                    sample.morsels.insert(e.id(), morsel.storage());
+                }
+                Kind::Nest { .. } | Kind::Obstacle => (),
+            }
+        }
+
+        sample
+    }
+
+    /// Gets the columns of the row of this sample.
+    pub fn columns(&self) -> Vec<(String, Value)> {
+        let morsels = self
+            .morsels
+            .iter()
+            .map(|(id, storage)| (id.to_string(), Value::from(*storage)))
+            .collect();
+
+        let colonies = serde_json::to_value(&self.colonies).unwrap_or_default();
+
+        vec![
+            ("generation".into(), self.generation.into()),
+            ("delivered".into(), self.delivered.into()),
+            ("colonies".into(), colonies),
+            ("foraging".into(), self.foraging.into()),
+            ("carrying".into(), self.carrying.into()),
+            ("lost".into(), self.lost.into()),
+            ("deaths".into(), self.deaths.into()),
+            ("births".into(), self.births.into()),
+            ("colonyPheros".into(), self.colony_pheros.count.into()),
+            ("colonyStrength".into(), self.colony_pheros.strength.into()),
+            ("foodPheros".into(), self.food_pheros.count.into()),
+            ("foodStrength".into(), self.food_pheros.strength.into()),
+            ("morsels".into(), Value::Object(morsels)),
+            ("discovered".into(), self.discovered.clone().into()),
+        ]
+    }
+}
+
+impl Metrics {
+    /// Starts collecting the metrics of the given simulation, from its current
+    /// generation.
+    pub fn new(state: &State) -> Self {
+        Self {
+            samples: vec![Sample::new(state, None)],
+            discoveries: BTreeMap::new(),
+        }
+    }
+
+    /// Takes a sample of the current generation of the given simulation.
+    pub fn sample(&mut self, state: &State) {
+        let previous = self.latest();
+        let mut sample = Sample::new(state, Some(previous));
+
+        // a Morsel is discovered as soon as its storage decreases
+        for (&id, &storage) in &sample.morsels {
+            let before = previous.morsels.get(&id).copied().unwrap_or(storage);
+            if storage < before && !self.discoveries.contains_key(&id) {
+                sample.discovered.push(id);
+            }
+        }
+        for &id in &sample.discovered {
+            self.discoveries.insert(id, sample.generation);
+        }
+
+        self.samples.push(sample);
+    }
+
+    /// Gets all the samples collected so far, one per generation.
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Gets the sample of the latest generation.
+    pub fn latest(&self) -> &Sample {
+        self.samples.last().expect("No metrics sample")
+    }
+
+    /// Gets the generation each Morsel was first discovered at.
+    pub fn discoveries(&self) -> &BTreeMap<Id, u64> {
+        &self.discoveries
+    }
+
+    /// Exports all the samples to the file with the given path, as CSV or
+    /// JSON Lines according to its extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = export::Writer::create(path)?;
+        for sample in &self.samples {
+            writer.write(sample.columns())?;
+        }
+        writer.flush()
+    }
+}
@@ -1,7 +1,22 @@
 pub use conf::*;
 pub use context::*;
+pub use field::{PheroField, Traces};
+pub use headless::Summary;
+pub use map::Map;
+pub use metrics::{ColonyStats, Metrics, PheroStats, Sample};
+pub use record::*;
+pub use snapshot::*;
 pub use state::*;
 
 pub mod conf;
 mod context;
+pub mod export;
+mod field;
+pub mod headless;
+mod map;
+mod metrics;
+mod placement;
+mod record;
+mod snapshot;
 mod state;
+pub mod sweep;
@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use semeion::*;
+use std::collections::BTreeSet;
+use std::f64::consts::TAU;
+
+use super::{Conf, Strategy};
+
+/// The maximum number of locations drawn for each Morsel before giving up on
+/// satisfying the placement constraints.
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// The number of locations drawn around each sample of the Poisson-disk
+/// sampling before it's no longer used to place new Morsels.
+const POISSON_DISK_CANDIDATES: usize = 30;
+
+/// The placement of the random Morsels, that keeps track of the Morsels
+/// placed so far to satisfy the constraints of the configuration.
+struct Placer<'a> {
+    conf: &'a Conf,
+    /// The minimum distance between any two Morsels.
+    min_distance: usize,
+    /// The locations of the Nests of all the colonies.
+    nests: Vec<Location>,
+    /// The locations of the obstacles, where no Morsel can be placed.
+    obstacles: BTreeSet<(i32, i32)>,
+    /// The locations of all the Morsels placed so far, including the ones
+    /// placed at a given location.
+    occupied: Vec<Location>,
+}
+
+/// Gets the locations of the random Morsels of the given configuration,
+/// according to its placement strategy and constraints.
+pub(super) fn morsels(
+    conf: &Conf,
+    rng: &mut impl Rng,
+) -> Result<Vec<Location>> {
+    let placement = &conf.morsels.placement;
+    let min_distance = match placement.strategy {
+        Strategy::PoissonDisk { radius } => radius.max(placement.min_distance),
+        _ => placement.min_distance,
+    };
+    let mut placer = Placer {
+        conf,
+        min_distance,
+        nests: conf
+            .colonies()
+            .iter()
+            .map(|colony| colony.location.into())
+            .collect(),
+        obstacles: conf.obstacles(),
+        occupied: conf
+            .morsels
+            .placed
+            .iter()
+            .map(|m| m.location.into())
+            .collect(),
+    };
+    let count = conf.morsels.count;
+
+    let mut locations = Vec::with_capacity(count);
+    match conf.morsels.placement.strategy {
+        Strategy::Uniform => {
+            for _ in 0..count {
+                locations.push(placer.place(rng, Placer::uniform)?);
+            }
+        }
+        Strategy::Clustered { patches, radius } => {
+            let centers: Vec<_> =
+                (0..patches).map(|_| placer.uniform(rng)).collect();
+            for _ in 0..count {
+                let location = placer.place(rng, |placer, rng| {
+                    let center = centers[rng.gen_range(0..centers.len())];
+                    placer.within(center, radius, rng)
+                })?;
+                locations.push(location);
+            }
+        }
+        Strategy::Ring { distance } => {
+            // each Nest has its own ring, with the Morsels spread evenly
+            // among them
+            for i in 0..count {
+                let nest = placer.nests[i % placer.nests.len()];
+                let location = placer.place(rng, |placer, rng| {
+                    let angle = rng.gen_range(0.0..TAU);
+                    placer.at(nest, distance as f64, angle)
+                })?;
+                locations.push(location);
+            }
+        }
+        Strategy::PoissonDisk { radius } => {
+            // Bridson's algorithm: new samples are drawn in the annulus
+            // between one and two radii around the samples placed so far
+            let mut active = Vec::new();
+            while locations.len() < count {
+                if active.is_empty() {
+                    let location = placer.place(rng, Placer::uniform)?;
+                    active.push(location);
+                    locations.push(location);
+                    continue;
+                }
+
+                let i = rng.gen_range(0..active.len());
+                let sample = (0..POISSON_DISK_CANDIDATES)
+                    .map(|_| {
+                        let distance = rng.gen_range(radius..=radius * 2);
+                        let angle = rng.gen_range(0.0..TAU);
+                        placer.at(active[i], distance as f64, angle)
+                    })
+                    .find(|&candidate| placer.is_valid(candidate));
+                match sample {
+                    Some(location) => {
+                        placer.occupied.push(location);
+                        active.push(location);
+                        locations.push(location);
+                    }
+                    None => {
+                        active.swap_remove(i);
+                    }
+                }
+            }
+        }
+    }
+    Ok(locations)
+}
+
+/// Gets the location of a new Morsel appearing anywhere in the environment
+/// that satisfies the placement constraints, given the locations of the
+/// Morsels already in it, if there is any.
+pub(super) fn spawned_morsel(
+    conf: &Conf,
+    morsels: Vec<Location>,
+    rng: &mut impl Rng,
+) -> Option<Location> {
+    let mut placer = Placer {
+        conf,
+        min_distance: conf.morsels.placement.min_distance,
+        nests: conf
+            .colonies()
+            .iter()
+            .map(|colony| colony.location.into())
+            .collect(),
+        obstacles: conf.obstacles(),
+        occupied: morsels,
+    };
+    placer.place(rng, Placer::uniform).ok()
+}
+
+impl<'a> Placer<'a> {
+    /// Places a new Morsel at the first location drawn with the given
+    /// function that satisfies the placement constraints.
+    fn place<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        mut draw: impl FnMut(&Self, &mut R) -> Location,
+    ) -> Result<Location> {
+        for _ in 0..MAX_ATTEMPTS {
+            let location = draw(self, rng);
+            if self.is_valid(location) {
+                self.occupied.push(location);
+                return Ok(location);
+            }
+        }
+        Err(anyhow!(
+            "Cannot place {} Morsels satisfying the placement constraints",
+            self.conf.morsels.count
+        ))
+    }
+
+    /// Returns true only if a Morsel can be placed at the given location,
+    /// that is not an obstacle, and is far enough from all the Nests and from
+    /// all the other Morsels.
+    fn is_valid(&self, location: Location) -> bool {
+        let placement = &self.conf.morsels.placement;
+        let env = &self.conf.env;
+        let distance = |other| {
+            env.topology.distance(
+                location,
+                other,
+                env.dimension,
+                Distance::Euclidean,
+            )
+        };
+        !self.obstacles.contains(&location.into())
+            && self
+                .nests
+                .iter()
+                .all(|&nest| distance(nest) >= placement.min_nest_distance)
+            && self
+                .occupied
+                .iter()
+                .all(|&other| distance(other) >= self.min_distance)
+    }
+
+    /// Draws a location anywhere in the environment.
+    fn uniform(&self, rng: &mut impl Rng) -> Location {
+        let (width, height) = self.conf.env.dimension;
+        (rng.gen_range(0..width), rng.gen_range(0..height)).into()
+    }
+
+    /// Draws a location within the given radius from the given center.
+    fn within(
+        &self,
+        center: Location,
+        radius: usize,
+        rng: &mut impl Rng,
+    ) -> Location {
+        let distance = rng.gen_range(0.0..=radius as f64);
+        let angle = rng.gen_range(0.0..TAU);
+        self.at(center, distance, angle)
+    }
+
+    /// Gets the location at the given distance from the given origin, in the
+    /// direction of the given angle, wrapping around, reflected back or
+    /// stopped by the environment edges according to its topology.
+    fn at(&self, origin: Location, distance: f64, angle: f64) -> Location {
+        let env = &self.conf.env;
+        let offset = Offset {
+            x: (distance * angle.cos()).round() as i32,
+            y: (distance * angle.sin()).round() as i32,
+        };
+        env.topology.translate(origin, offset, env.dimension)
+    }
+}
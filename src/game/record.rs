@@ -0,0 +1,478 @@
+use anyhow::{anyhow, Result};
+use semeion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::{Conf, EntitySnapshot, PheroField, Snapshot, State, Traces};
+use crate::entity::{self, ant, phero};
+
+/// The number of generations between two consecutive keyframes of a replay.
+const KEYFRAME_INTERVAL: u64 = 500;
+
+/// Something that happened during a generation of the simulation, recorded to
+/// replay the simulation without having to simulate it again.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "event")]
+pub enum Event {
+    /// An Ant moved to a new location.
+    #[serde(rename_all = "camelCase")]
+    Move {
+        ant: Id,
+        #[serde(with = "entity::location")]
+        to: Location,
+    },
+    /// An Ant added the given concentration to the pheromone in its tile.
+    #[serde(rename_all = "camelCase")]
+    Deposit {
+        scent: phero::Scent,
+        #[serde(default)]
+        colony: entity::Colony,
+        #[serde(with = "entity::location")]
+        location: Location,
+        concentration: u64,
+    },
+    /// An Ant cleared the concentration of a possibly misleading pheromone.
+    #[serde(rename_all = "camelCase")]
+    Suppress {
+        scent: phero::Scent,
+        #[serde(default)]
+        colony: entity::Colony,
+        #[serde(with = "entity::location")]
+        location: Location,
+    },
+    /// An Ant took a unit of food from a Morsel.
+    #[serde(rename_all = "camelCase")]
+    Pickup { ant: Id, morsel: Id },
+    /// An Ant dropped the food it was carrying into the Nest.
+    #[serde(rename_all = "camelCase")]
+    DropOff { ant: Id },
+    /// An Ant ate a unit of food from the given Morsel, or from its Nest if
+    /// none.
+    #[serde(rename_all = "camelCase")]
+    Eat {
+        ant: Id,
+        #[serde(default)]
+        morsel: Option<Id>,
+    },
+    /// An Ant died of hunger.
+    #[serde(rename_all = "camelCase")]
+    Death { ant: Id },
+    /// The Nest of a colony spawned a new Ant at the given location.
+    #[serde(rename_all = "camelCase")]
+    Birth {
+        ant: Id,
+        colony: entity::Colony,
+        #[serde(with = "entity::location")]
+        location: Location,
+    },
+    /// A new Morsel was added to the environment.
+    #[serde(rename_all = "camelCase")]
+    AddMorsel {
+        morsel: Id,
+        #[serde(with = "entity::location")]
+        location: Location,
+        storage: u64,
+    },
+    /// A Morsel was removed from the environment, with all its food.
+    #[serde(rename_all = "camelCase")]
+    RemoveMorsel { morsel: Id },
+    /// The Nest of a colony was moved to a new location.
+    #[serde(rename_all = "camelCase")]
+    MoveNest {
+        #[serde(default)]
+        colony: entity::Colony,
+        #[serde(with = "entity::location")]
+        to: Location,
+    },
+}
+
+impl Event {
+    /// Gets the Ant that caused the event, if any.
+    ///
+    /// The pheromones deposited or suppressed don't keep track of the Ant, so
+    /// it's given when they're recorded instead.
+    pub fn ant(&self) -> Option<Id> {
+        match *self {
+            Self::Move { ant, .. }
+            | Self::Pickup { ant, .. }
+            | Self::DropOff { ant }
+            | Self::Eat { ant, .. }
+            | Self::Death { ant } => Some(ant),
+            _ => None,
+        }
+    }
+}
+
+/// All the events recorded in a single generation.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Generation {
+    generation: u64,
+    events: Vec<Event>,
+}
+
+/// Writes the events of a running simulation to a recording file.
+///
+/// The recording is a JSON Lines file, where the first line contains the
+/// snapshot of the simulation when the recording started, and each of the
+/// following lines contains the events of a single generation.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Creates a new recording file with the given path, starting from the
+    /// current state of the simulation.
+    pub fn create(path: impl AsRef<Path>, state: &State) -> Result<Self> {
+        let path = path.as_ref();
+        log::info!("Recording simulation to {:?}", path);
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &state.snapshot())?;
+        writeln!(writer)?;
+        Ok(Self { writer })
+    }
+
+    /// Writes the events that took place in the given generation.
+    pub fn record(
+        &mut self,
+        generation: u64,
+        events: Vec<Event>,
+    ) -> Result<()> {
+        let generation = Generation { generation, events };
+        serde_json::to_writer(&mut self.writer, &generation)?;
+        writeln!(self.writer)?;
+        Ok(())
+    }
+
+    /// Flushes everything recorded so far to the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A recording of a simulation, loaded from file.
+#[derive(Debug)]
+pub struct Recording {
+    /// The snapshot of the simulation when the recording started.
+    pub initial: Snapshot,
+    /// The events of each generation that followed, in order.
+    pub generations: Vec<Vec<Event>>,
+}
+
+impl Recording {
+    /// Reads the recording from the file with the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        log::info!("Loading recording from {:?}", path);
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let header = lines.next().ok_or_else(|| anyhow!("Empty recording"))?;
+        let initial: Snapshot = serde_json::from_str(&header?)?;
+
+        let mut generations = Vec::new();
+        for line in lines {
+            let generation: Generation = serde_json::from_str(&line?)?;
+            let expected = initial.generation + generations.len() as u64 + 1;
+            if generation.generation != expected {
+                return Err(anyhow!(
+                    "Expected generation {} but found {} in recording",
+                    expected,
+                    generation.generation
+                ));
+            }
+            generations.push(generation.events);
+        }
+
+        Ok(Self {
+            initial,
+            generations,
+        })
+    }
+}
+
+/// The state of a recorded simulation at a given generation, reconstructed
+/// from its events.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// The generation this frame represents.
+    pub generation: u64,
+    /// The location and the amount of food delivered so far to the Nest of
+    /// each colony.
+    pub nests: Vec<(Location, u64)>,
+    /// The amount of food delivered so far to all the Nests.
+    pub delivered: u64,
+    /// The location, activity and colony of each Ant.
+    pub ants: BTreeMap<Id, (Location, ant::Activity, entity::Colony)>,
+    /// The location and remaining storage of each Morsel that still has food,
+    /// or that regrows it.
+    pub morsels: BTreeMap<Id, (Location, u64)>,
+    /// The concentration of the pheromones of each scent and colony.
+    pub pheros: PheroField,
+    /// The location of each obstacle.
+    pub obstacles: Vec<Location>,
+    /// The number of Ants that died of hunger so far.
+    pub deaths: u64,
+    /// The number of Ants spawned by the Nests so far.
+    pub births: u64,
+}
+
+impl Frame {
+    /// Constructs the frame of the simulation recorded in the given snapshot.
+    pub fn new(snapshot: &Snapshot) -> Self {
+        let mut frame = Self {
+            generation: snapshot.generation,
+            nests: vec![Default::default(); snapshot.conf.colonies().len()],
+            delivered: 0,
+            ants: BTreeMap::new(),
+            morsels: BTreeMap::new(),
+            pheros: PheroField::new(&snapshot.conf),
+            obstacles: Vec::new(),
+            deaths: snapshot.deaths.iter().sum(),
+            births: snapshot.births.iter().sum(),
+        };
+
+        for phero in &snapshot.pheromones {
+            frame.pheros.set(
+                phero.scent,
+                phero.colony,
+                phero.location,
+                phero.concentration,
+            );
+        }
+
+        for entity in &snapshot.entities {
+            match entity {
+                EntitySnapshot::Nest {
+                    colony,
+                    location,
+                    state,
+                    ..
+                } => {
+                    frame.nests[*colony] = (*location, state.delivered());
+                    frame.delivered += state.delivered();
+                }
+                EntitySnapshot::Morsel {
+                    id,
+                    location,
+                    storage,
+                } => {
+                    frame.morsels.insert(*id, (*location, *storage));
+                }
+                EntitySnapshot::Ant {
+                    id,
+                    location,
+                    state,
+                } => {
+                    let ant = (*location, state.activity(), state.colony());
+                    frame.ants.insert(*id, ant);
+                }
+                EntitySnapshot::Obstacle { location, .. } => {
+                    frame.obstacles.push(*location);
+                }
+            }
+        }
+
+        frame
+    }
+
+    /// Gets the total amount of food in play, either delivered to the Nests,
+    /// in the Morsels or carried by the Ants.
+    pub fn total_storage(&self) -> u64 {
+        let morsels: u64 = self.morsels.values().map(|(_, s)| s).sum();
+        let carried = self
+            .ants
+            .values()
+            .filter(|(_, activity, _)| *activity == ant::Activity::Carrying)
+            .count() as u64;
+        self.delivered + morsels + carried
+    }
+
+    /// Moves the frame to the next generation by applying the events that
+    /// took place in it, while the pheromones evaporate and spread, and the
+    /// Morsels regrow, according to the given configuration.
+    pub fn apply(&mut self, events: &[Event], conf: &Conf) {
+        // the changes to the pheromones only take effect once the generation
+        // is over
+        let mut traces = Traces::default();
+
+        for event in events {
+            match *event {
+                Event::Move { ant, to } => {
+                    if let Some((location, ..)) = self.ants.get_mut(&ant) {
+                        *location = to;
+                    }
+                }
+                Event::Deposit {
+                    scent,
+                    colony,
+                    location,
+                    concentration,
+                } => {
+                    let key = (scent, colony, location);
+                    traces.deposits.push((key, concentration));
+                }
+                Event::Suppress {
+                    scent,
+                    colony,
+                    location,
+                } => traces.suppressed.push((scent, colony, location)),
+                Event::Pickup { ant, morsel } => {
+                    if let Some((_, storage)) = self.morsels.get_mut(&morsel) {
+                        *storage = storage.saturating_sub(1);
+                    }
+                    if let Some((_, activity, _)) = self.ants.get_mut(&ant) {
+                        *activity = ant::Activity::Carrying;
+                    }
+                }
+                Event::DropOff { ant } => {
+                    self.delivered += 1;
+                    if let Some((_, activity, colony)) = self.ants.get_mut(&ant)
+                    {
+                        *activity = ant::Activity::Foraging;
+                        if let Some((_, delivered)) =
+                            self.nests.get_mut(*colony)
+                        {
+                            *delivered += 1;
+                        }
+                    }
+                }
+                // the food eaten from the Nests doesn't change the food
+                // delivered to them
+                Event::Eat {
+                    morsel: Some(morsel),
+                    ..
+                } => {
+                    if let Some((_, storage)) = self.morsels.get_mut(&morsel) {
+                        *storage = storage.saturating_sub(1);
+                    }
+                }
+                Event::Eat { morsel: None, .. } => (),
+                Event::Death { ant } => {
+                    self.ants.remove(&ant);
+                    self.deaths += 1;
+                }
+                Event::Birth {
+                    ant,
+                    colony,
+                    location,
+                } => {
+                    let activity = ant::Activity::Foraging;
+                    self.ants.insert(ant, (location, activity, colony));
+                    self.births += 1;
+                }
+                Event::AddMorsel {
+                    morsel,
+                    location,
+                    storage,
+                } => {
+                    self.morsels.insert(morsel, (location, storage));
+                }
+                Event::RemoveMorsel { morsel } => {
+                    self.morsels.remove(&morsel);
+                }
+                Event::MoveNest { colony, to } => {
+                    if let Some((location, _)) = self.nests.get_mut(colony) {
+                        *location = to;
+                    }
+                }
+            }
+        }
+
+        self.pheros.update(conf, &traces);
+        if conf.morsels.regrowth.is_none() {
+            self.morsels.retain(|_, (_, storage)| *storage > 0);
+        }
+        self.generation += 1;
+        for (_, storage) in self.morsels.values_mut() {
+            *storage = conf.morsels.regrow(*storage, self.generation);
+        }
+    }
+}
+
+/// Replays a recorded simulation, allowing to seek any recorded generation.
+pub struct Replay {
+    recording: Recording,
+    /// The frames of the generations multiple of the keyframe interval, used
+    /// as starting points when seeking.
+    keyframes: Vec<Frame>,
+    /// The frame of the current generation.
+    frame: Frame,
+}
+
+impl Replay {
+    /// Constructs a new Replay positioned at the first recorded generation.
+    pub fn new(recording: Recording) -> Self {
+        let first = Frame::new(&recording.initial);
+
+        let mut keyframes = vec![first.clone()];
+        let mut frame = first.clone();
+        for events in &recording.generations {
+            frame.apply(events, &recording.initial.conf);
+            if (frame.generation - first.generation)
+                .is_multiple_of(KEYFRAME_INTERVAL)
+            {
+                keyframes.push(frame.clone());
+            }
+        }
+
+        Self {
+            recording,
+            keyframes,
+            frame: first,
+        }
+    }
+
+    /// Gets the configuration of the recorded simulation.
+    pub fn conf(&self) -> &Conf {
+        &self.recording.initial.conf
+    }
+
+    /// Gets the frame of the current generation.
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Gets the first recorded generation.
+    pub fn first_generation(&self) -> u64 {
+        self.recording.initial.generation
+    }
+
+    /// Gets the last recorded generation.
+    pub fn last_generation(&self) -> u64 {
+        self.first_generation() + self.recording.generations.len() as u64
+    }
+
+    /// Moves to the next recorded generation, returns false if the current
+    /// generation is already the last one.
+    pub fn step(&mut self) -> bool {
+        let index = (self.frame.generation - self.first_generation()) as usize;
+        match self.recording.generations.get(index) {
+            Some(events) => {
+                self.frame.apply(events, &self.recording.initial.conf);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves to the given generation, clamped within the recorded ones.
+    pub fn seek(&mut self, generation: u64) {
+        let generation =
+            generation.clamp(self.first_generation(), self.last_generation());
+        let offset = generation - self.first_generation();
+        // start from the closest keyframe, unless the current frame is closer
+        if generation < self.frame.generation
+            || generation - self.frame.generation > offset % KEYFRAME_INTERVAL
+        {
+            let keyframe = (offset / KEYFRAME_INTERVAL) as usize;
+            self.frame = self.keyframes[keyframe].clone();
+        }
+        while self.frame.generation < generation {
+            self.step();
+        }
+    }
+}
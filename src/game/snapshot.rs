@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use semeion::*;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use std::{fs, path::Path, sync::Arc};
+
+use super::{Conf, Context, PlacedPhero, State};
+use crate::entity::{self, ant, downcast_state, morsel, nest, Kind};
+
+/// A complete snapshot of a running simulation, from which the simulation can
+/// be restored to continue exactly where it left off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// The configuration of the simulation, including its seed.
+    pub conf: Conf,
+    /// The generation the snapshot was taken at.
+    pub generation: u64,
+    /// The ID that will be assigned to the next Entity.
+    pub next_id: Id,
+    /// The snapshots of all the entities in the environment.
+    pub entities: Vec<EntitySnapshot>,
+    /// All the pheromones left in the environment.
+    #[serde(default)]
+    pub pheromones: Vec<PlacedPhero>,
+    /// The number of Ants of each colony that died of hunger.
+    #[serde(default)]
+    pub deaths: Vec<u64>,
+    /// The number of Ants of each colony spawned by its Nest.
+    #[serde(default)]
+    pub births: Vec<u64>,
+}
+
+/// The snapshot of a single Entity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum EntitySnapshot {
+    #[serde(rename_all = "camelCase")]
+    Nest {
+        id: Id,
+        #[serde(default)]
+        colony: entity::Colony,
+        #[serde(with = "entity::location")]
+        location: Location,
+        state: nest::State,
+    },
+    #[serde(rename_all = "camelCase")]
+    Morsel {
+        id: Id,
+        #[serde(with = "entity::location")]
+        location: Location,
+        storage: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Ant {
+        id: Id,
+        #[serde(with = "entity::location")]
+        location: Location,
+        state: Box<ant::State>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Obstacle {
+        id: Id,
+        #[serde(with = "entity::location")]
+        location: Location,
+    },
+}
+
+impl Snapshot {
+    /// Reads a snapshot from the file with the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        log::info!("Loading snapshot from {:?}", path);
+        let contents = fs::read_to_string(path)?;
+        let snapshot = serde_json::from_str(&contents)?;
+        Ok(snapshot)
+    }
+
+    /// Writes the snapshot to the file with the given path.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        log::info!("Saving snapshot to {:?}", path);
+        let contents = serde_json::to_string(self)?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+impl<'e> State<'e> {
+    /// Takes a snapshot of the current state of the simulation.
+    pub fn snapshot(&self) -> Snapshot {
+        let entities = self
+            .env
+            .entities()
+            .map(|e| {
+                let id = e.id();
+                let location = e.location().expect("Invalid entity location");
+                match e.kind() {
+                    Kind::Nest { colony } => EntitySnapshot::Nest {
+                        id,
+                        colony,
+                        location,
+                        state: *downcast_state(e),
+                    },
+                    Kind::Morsel => EntitySnapshot::Morsel {
+                        id,
+                        location,
+                        storage: downcast_state::<morsel::State>(e).storage(),
+                    },
+                    Kind::Ant => EntitySnapshot::Ant {
+                        id,
+                        location,
+                        state: Box::new(
+                            downcast_state::<ant::State>(e).clone(),
+                        ),
+                    },
+                    Kind::Obstacle => EntitySnapshot::Obstacle { id, location },
+                }
+            })
+            .collect();
+
+        let pheromones = self
+            .context
+            .pheros()
+            .pheros()
+            .map(|((scent, colony, location), concentration)| PlacedPhero {
+                scent,
+                colony,
+                location: location.into(),
+                concentration,
+            })
+            .collect();
+
+        Snapshot {
+            conf: self.context.conf.clone(),
+            generation: self.generation(),
+            next_id: self.context.next_id(),
+            entities,
+            pheromones,
+            deaths: self.context.deaths(),
+            births: self.context.births(),
+        }
+    }
+
+    /// Restores the state of the simulation recorded in the given snapshot.
+    pub fn restore(snapshot: Snapshot) -> Result<Self> {
+        snapshot.conf.validate()?;
+        let seed = snapshot.conf.seed;
+        let mut context = Context::new(snapshot.conf);
+        debug_assert_eq!(seed, Some(context.seed()));
+        context.set_next_id(snapshot.next_id);
+        context.set_deaths(snapshot.deaths);
+        context.set_births(snapshot.births);
+        let context = Arc::new(context);
+
+        let mut env = Environment::new(context.conf.env.dimension);
+        for entity in snapshot.entities {
+            match entity {
+                EntitySnapshot::Nest {
+                    id,
+                    colony,
+                    location,
+                    state,
+                } => env.insert(entity::Nest::restore(
+                    id,
+                    colony,
+                    location,
+                    state,
+                    Arc::clone(&context),
+                )),
+                EntitySnapshot::Morsel {
+                    id,
+                    location,
+                    storage,
+                } => env.insert(entity::Morsel::restore(
+                    id, location, storage, &context,
+                )),
+                EntitySnapshot::Ant {
+                    id,
+                    location,
+                    state,
+                } => env.insert(entity::Ant::restore(
+                    id,
+                    location,
+                    *state,
+                    Arc::clone(&context),
+                )),
+                EntitySnapshot::Obstacle { id, location } => {
+                    env.insert(entity::Obstacle::restore(id, location))
+                }
+            }
+        }
+
+        let mut pheros = context.pheros_mut();
+        for phero in snapshot.pheromones {
+            pheros.set(
+                phero.scent,
+                phero.colony,
+                phero.location,
+                phero.concentration,
+            );
+        }
+        drop(pheros);
+
+        for colony in 0..context.conf.colonies().len() {
+            if env.count_kind(&Kind::Nest { colony }) != 1 {
+                return Err(anyhow!(
+                    "The snapshot must contain exactly one Nest for colony {}",
+                    colony
+                ));
+            }
+        }
+
+        Ok(Self {
+            env,
+            context,
+            first_generation: snapshot.generation,
+            recorder: None,
+            metrics: None,
+            metrics_path: None,
+            started: Instant::now(),
+        })
+    }
+}
@@ -1,129 +1,385 @@
-use ggez::{event, graphics, mint, timer};
-use rand::{rngs::StdRng, Rng, SeedableRng};
+use anyhow::{anyhow, bail};
+use rand::Rng;
 use semeion::*;
-use std::{process, sync::Arc};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::{entity, game};
 
+/// The random stream used to populate the environment, reserved so that it
+/// cannot overlap with the stream of any Entity.
+const WORLD_STREAM: u64 = u64::MAX;
+
+/// The random stream used to spawn the Morsels of generation 0, while each
+/// following generation uses the stream that precedes the one of the previous
+/// generation, so that none of them overlaps with the stream of any Entity.
+const SPAWN_STREAM: u64 = WORLD_STREAM - 1;
+
 /// The global state of the game.
 pub struct State<'e> {
-    /// The environment where the ant colony simulation takes place.
-    pub env: Environment<'e, entity::Kind, ggez::Context>,
+    /// The environment where the ant colony simulation takes place, which
+    /// only moves forward through `State::nextgen`.
+    pub(crate) env: Environment<'e, entity::Kind, entity::Context>,
     /// The game context.
-    context: Arc<game::Context>,
+    pub(crate) context: Arc<game::Context>,
+    /// The generation the environment started from.
+    pub(super) first_generation: u64,
+    /// The recorder of the simulation events, if being recorded.
+    pub(super) recorder: Option<game::Recorder>,
+    /// The collector of the simulation metrics, if being collected.
+    pub(super) metrics: Option<game::Metrics>,
+    /// The path the metrics are exported to when the simulation is finished.
+    pub(super) metrics_path: Option<PathBuf>,
+    /// The instant the simulation started running, or was restored.
+    pub(super) started: Instant,
 }
 
 impl<'e> State<'e> {
     /// Constructs the game state by populating the environment with the initial
-    /// entities.
-    pub fn new(context: Arc<game::Context>) -> ggez::GameResult<Self> {
+    /// entities. The map of the configuration, if any, must have already been
+    /// laid out with `Conf::with_map`, so that the entities see it.
+    pub fn new(context: Arc<game::Context>) -> anyhow::Result<Self> {
+        if let Some(map) = &context.conf.map {
+            bail!("The map {:?} has not been laid out", map);
+        }
+        context.conf.validate()?;
         let mut env = Environment::new(context.conf.env.dimension);
         debug_assert_eq!(env.dimension(), context.conf.env.dimension.into());
 
-        // populate the environment
-        env.insert(entity::Grid::new(Arc::clone(&context)));
-        let nest_location = context.conf.nest.location;
-        env.insert(entity::Nest::new(nest_location, Arc::clone(&context)));
-
-        for _ in 0..context.conf.count(entity::Kind::Ant) {
-            env.insert(entity::Ant::new(nest_location, Arc::clone(&context)));
+        // populate the environment, starting from the Nest and the Ants of
+        // each colony
+        for (colony, conf) in context.conf.colonies().iter().enumerate() {
+            env.insert(entity::Nest::new(
+                colony,
+                conf.location,
+                Arc::clone(&context),
+            ));
+            for _ in 0..conf.count {
+                env.insert(entity::Ant::new(
+                    colony,
+                    conf.location,
+                    Arc::clone(&context),
+                ));
+            }
         }
 
-        let mut rng = StdRng::seed_from_u64(context.conf.seed.unwrap_or(0));
-        for _ in 0..context.conf.count(entity::Kind::Morsel) {
-            let location = (
-                rng.gen_range(0..env.dimension().x),
-                rng.gen_range(0..env.dimension().y),
-            );
+        let mut rng = context.rng(WORLD_STREAM);
+        for location in game::placement::morsels(&context.conf, &mut rng)? {
             env.insert(entity::Morsel::new(
                 location,
-                Lifespan::with_span(context.conf.morsels.storage),
+                context.conf.morsels.storage,
+                Arc::clone(&context),
+            ));
+        }
+
+        for morsel in &context.conf.morsels.placed {
+            env.insert(entity::Morsel::new(
+                morsel.location,
+                morsel.storage,
                 Arc::clone(&context),
             ));
         }
 
-        Ok(Self { env, context })
+        let mut pheros = context.pheros_mut();
+        for phero in &context.conf.pheromones.placed {
+            pheros.set(
+                phero.scent,
+                phero.colony,
+                phero.location,
+                phero.concentration,
+            );
+        }
+        drop(pheros);
+
+        for location in context.conf.obstacles() {
+            env.insert(entity::Obstacle::new(location, Arc::clone(&context)));
+        }
+
+        Ok(Self {
+            env,
+            context,
+            first_generation: 0,
+            recorder: None,
+            metrics: None,
+            metrics_path: None,
+            started: Instant::now(),
+        })
     }
 
-    /// Returns true only if the simulation is over, that is all the food has
-    /// been moved from the morsels to the nest.
-    pub fn is_simulation_over(&self) -> bool {
-        debug_assert!(self.storage() <= self.context.conf.total_storage());
-        self.storage() == self.context.conf.total_storage()
+    /// Gets the environment where the simulation takes place, that can only
+    /// be moved to the next generation with `State::nextgen`, which also
+    /// updates the pheromones and the Morsels, and records the events and the
+    /// metrics of the generation.
+    pub fn env(&self) -> &Environment<'e, entity::Kind, entity::Context> {
+        &self.env
     }
 
-    /// Gets the amount of food currently stored in the Nest.
-    fn storage(&self) -> u64 {
-        self.env
-            .entities()
-            .find(|e| e.kind() == entity::Kind::Nest)
-            .and_then(|e| e.state())
-            .and_then(|s| s.as_any().downcast_ref::<entity::nest::State>())
-            .expect("Cannot get Nest state")
-            .storage()
-    }
-
-    /// Draw simulation statistics.
-    fn draw_stats(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
-        let mut text = format!(
-            "Collected: {}/{}",
-            self.storage(),
-            self.context.conf.total_storage()
-        );
-        text += &format!("\nGeneration: {}", self.env.generation());
-
-        let foreground = graphics::Color::WHITE;
-        let fragment = graphics::TextFragment::new(text).color(foreground);
-        let text = graphics::Text::new(fragment);
-
-        let dest = mint::Point2 { x: 10.0, y: 10.0 };
-        graphics::draw(ctx, &text, graphics::DrawParam::default().dest(dest))?;
+    /// Gets the game context.
+    pub fn context(&self) -> &game::Context {
+        &self.context
+    }
+
+    /// Gets the current generation of the simulation, that takes into account
+    /// the generations simulated before the state was restored.
+    pub fn generation(&self) -> u64 {
+        self.first_generation + self.env.generation()
+    }
+
+    /// Moves the environment forward to the next generation, recording its
+    /// events if the simulation is being recorded.
+    pub fn nextgen(&mut self) -> anyhow::Result<()> {
+        self.env.nextgen().map_err(|e| {
+            anyhow!("Cannot move to the next generation: {}", e)
+        })?;
+
+        // the pheromones left by the Ants only take effect once all of them
+        // have acted upon the ones of the previous generation
+        self.context.update_pheros();
+        self.spawn_morsels();
+        self.regrow_morsels();
+        let events = self.context.take_events();
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(
+                self.first_generation + self.env.generation(),
+                events,
+            )?;
+        }
+
+        if let Some(mut metrics) = self.metrics.take() {
+            metrics.sample(self);
+            self.metrics = Some(metrics);
+        }
         Ok(())
     }
-}
 
-impl<'e> event::EventHandler<ggez::GameError> for State<'e> {
-    /// Updates the game state by moving the environment forward to the next
-    /// generation.
-    fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
-        let target_fps = self.context.conf.fps;
-        let mut step = || {
-            self.env
-                .nextgen()
-                .expect("Cannot move to the next generation");
-
-            if self.is_simulation_over() {
-                log::info!(
-                    "Simulation over after {} generations",
-                    self.env.generation()
-                );
-                process::exit(0);
-            }
+    /// Adds the new Morsels appearing in the current generation, according to
+    /// the schedule and the rate of the configuration.
+    fn spawn_morsels(&mut self) {
+        let conf = &self.context.conf;
+        let spawning = match &conf.morsels.spawning {
+            Some(spawning) => spawning,
+            None => return,
         };
+        let generation = self.generation();
+        let mut rng = self.context.rng(SPAWN_STREAM.wrapping_sub(generation));
+        let scheduled = spawning.schedule.iter().filter(|&&g| g == generation);
+        let count = scheduled.count() + rng.gen_bool(spawning.rate) as usize;
+
+        let storage = conf.morsels.spawned_storage();
+        for _ in 0..count {
+            let morsels = self
+                .env
+                .entities()
+                .filter(|e| e.kind() == entity::Kind::Morsel)
+                .filter_map(|e| e.location())
+                .collect();
+            let location = match game::placement::spawned_morsel(
+                conf, morsels, &mut rng,
+            ) {
+                Some(location) => location,
+                None => {
+                    log::warn!(
+                        "No room for a new Morsel at generation {}",
+                        generation
+                    );
+                    break;
+                }
+            };
 
-        if let Some(fps) = target_fps {
-            while timer::check_update_time(ctx, fps) {
-                step();
+            let morsel = entity::Morsel::new(
+                location,
+                storage,
+                Arc::clone(&self.context),
+            );
+            self.context.record(game::Event::AddMorsel {
+                morsel: morsel.id(),
+                location,
+                storage,
+            });
+            self.env.insert(morsel);
+        }
+    }
+
+    /// Lets the Morsels regrow, even the ones emptied by the Ants, according
+    /// to the regrowth of the configuration.
+    fn regrow_morsels(&mut self) {
+        let generation = self.generation();
+        let morsels = &self.context.conf.morsels;
+        if morsels.regrowth.is_none() {
+            return;
+        }
+        for e in self.env.entities_mut() {
+            if e.kind() != entity::Kind::Morsel {
+                continue;
             }
-        } else {
-            step();
+            let morsel: &mut entity::morsel::State =
+                entity::downcast_state_mut(e);
+            let storage = morsel.storage();
+            morsel.grow(morsels.regrow(storage, generation) - storage);
         }
+    }
 
+    /// Starts recording the simulation to the file with the given path.
+    pub fn record(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.stop_recording()?;
+        self.recorder = Some(game::Recorder::create(path, self)?);
+        self.context.set_recording(true);
         Ok(())
     }
 
-    /// Draws the environment with all its entities.
-    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
-        graphics::clear(ctx, self.context.conf.env.background.into());
+    /// Stops recording the simulation, flushing the recording file.
+    pub fn stop_recording(&mut self) -> anyhow::Result<()> {
+        self.context.set_recording(false);
+        self.context.take_events();
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
 
-        self.env
-            .draw(ctx, Transform::identity())
-            .expect("Cannot draw the environment");
+    /// Starts collecting the metrics of the simulation at each generation,
+    /// unless already collecting them. The metrics are exported to the given
+    /// path, if any, when the simulation is finished.
+    pub fn collect_metrics(&mut self, path: Option<PathBuf>) {
+        if self.metrics.is_none() {
+            self.metrics = Some(game::Metrics::new(self));
+        }
+        if path.is_some() {
+            self.metrics_path = path;
+        }
+    }
 
-        self.draw_stats(ctx)?;
+    /// Gets the metrics collected so far, if being collected.
+    pub fn metrics(&self) -> Option<&game::Metrics> {
+        self.metrics.as_ref()
+    }
 
-        graphics::present(ctx)?;
-        timer::yield_now();
+    /// Finishes the simulation by flushing its recording and exporting its
+    /// metrics, if any.
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        self.stop_recording()?;
+        if let (Some(metrics), Some(path)) = (&self.metrics, &self.metrics_path)
+        {
+            metrics.save(path)?;
+        }
         Ok(())
     }
+
+    /// Gets the wall time elapsed since the simulation started running, or
+    /// since it was restored.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Returns true only if the simulation is over, that is any of the end
+    /// conditions of the configuration is met.
+    pub fn is_simulation_over(&self) -> bool {
+        self.end_condition().is_some()
+    }
+
+    /// Gets the first end condition of the configuration that is met, if any.
+    pub fn end_condition(&self) -> Option<game::EndCondition> {
+        use game::EndCondition::*;
+        self.context
+            .conf
+            .end
+            .iter()
+            .copied()
+            .find(|&condition| match condition {
+                AllFood => self.is_food_collected(),
+                FoodPercentage(percentage) => {
+                    let total = self.total_storage() as f64;
+                    self.delivered() as f64 >= total * percentage / 100.0
+                }
+                MaxGenerations(max) => self.generation() >= max,
+                WallClock(secs) => self.elapsed().as_secs_f64() >= secs,
+                Never => false,
+            })
+    }
+
+    /// Returns true only if all the food has been moved from the morsels to
+    /// the nest.
+    pub fn is_food_collected(&self) -> bool {
+        self.delivered() == self.total_storage()
+    }
+
+    /// Gets the total amount of food in play, either delivered to the Nests,
+    /// in the Morsels or carried by the Ants, so that the food eaten or spent
+    /// once delivered still counts as collected.
+    pub fn total_storage(&self) -> u64 {
+        self.env
+            .entities()
+            .map(|e| match e.kind() {
+                entity::Kind::Morsel => {
+                    entity::downcast_state::<entity::morsel::State>(e).storage()
+                }
+                entity::Kind::Ant => {
+                    let state: &entity::ant::State = entity::downcast_state(e);
+                    (state.activity() == entity::ant::Activity::Carrying) as u64
+                }
+                _ => 0,
+            })
+            .sum::<u64>()
+            + self.delivered()
+    }
+
+    /// Gets the amount of food currently stored in the Nests of all the
+    /// colonies.
+    pub fn storage(&self) -> u64 {
+        self.colony_storages().iter().sum()
+    }
+
+    /// Gets the total amount of food delivered so far to the Nests of all the
+    /// colonies.
+    pub fn delivered(&self) -> u64 {
+        self.colony_deliveries().iter().sum()
+    }
+
+    /// Gets the statistics of each colony.
+    pub fn colonies(&self) -> Vec<game::ColonyStats> {
+        let mut colonies: Vec<_> = self
+            .colony_deliveries()
+            .into_iter()
+            .zip(self.context.deaths())
+            .zip(self.context.births())
+            .map(|((delivered, deaths), births)| game::ColonyStats {
+                delivered,
+                ants: 0,
+                deaths,
+                births,
+            })
+            .collect();
+        for e in self.env.entities() {
+            if e.kind() == entity::Kind::Ant {
+                let state: &entity::ant::State = entity::downcast_state(e);
+                let colony = state.colony();
+                colonies[colony].ants += 1;
+            }
+        }
+        colonies
+    }
+
+    /// Gets the amount of food currently stored in the Nest of each colony.
+    pub fn colony_storages(&self) -> Vec<u64> {
+        self.nest_states().iter().map(|s| s.storage()).collect()
+    }
+
+    /// Gets the total amount of food delivered so far to the Nest of each
+    /// colony.
+    pub fn colony_deliveries(&self) -> Vec<u64> {
+        self.nest_states().iter().map(|s| s.delivered()).collect()
+    }
+
+    /// Gets the state of the Nest of each colony.
+    fn nest_states(&self) -> Vec<entity::nest::State> {
+        let mut states =
+            vec![Default::default(); self.context.conf.colonies().len()];
+        for e in self.env.entities() {
+            if let entity::Kind::Nest { colony } = e.kind() {
+                states[colony] = *entity::downcast_state(e);
+            }
+        }
+        states
+    }
 }
@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{headless, Conf, Context, EndCondition, State, Summary};
+
+/// A grid of configuration values, where each combination of values is run
+/// as a separate headless simulation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Grid {
+    /// The values swept for each configuration field, identified by the path
+    /// of the field (e.g. `ants.memorySpan`).
+    pub parameters: BTreeMap<String, Vec<Value>>,
+    /// The seeds each combination is run with; if empty each combination is
+    /// run once with the seed of the base configuration.
+    #[serde(default)]
+    pub seeds: Vec<u64>,
+    /// The maximum number of generations of each run, in addition to the end
+    /// conditions of the configuration.
+    #[serde(default)]
+    pub max_generations: Option<u64>,
+    /// The maximum wall time of each run in seconds, in addition to the end
+    /// conditions of the configuration.
+    #[serde(default)]
+    pub timeout: Option<f64>,
+}
+
+/// A single run of the sweep.
+#[derive(Debug, Clone)]
+pub struct Run {
+    /// The value of each swept field.
+    pub parameters: Vec<(String, Value)>,
+    /// The configuration the simulation is run with.
+    pub conf: Conf,
+}
+
+/// The outcome of a single run of the sweep.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    /// The value of each swept field.
+    pub parameters: Vec<(String, Value)>,
+    /// The summary of the run.
+    pub summary: Summary,
+}
+
+impl Grid {
+    /// Reads the grid from the JSON file with the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        log::info!("Loading sweep grid from {:?}", path);
+        let contents = fs::read_to_string(path)?;
+        let grid = serde_json::from_str(&contents)?;
+        Ok(grid)
+    }
+
+    /// Gets all the runs of the sweep, one for each combination of values
+    /// (and seed), applied on top of the given base configuration, which must
+    /// all be valid.
+    pub fn runs(&self, base: &Conf) -> Result<Vec<Run>> {
+        // the cartesian product of all the parameters values
+        let mut combinations = vec![Vec::new()];
+        for (field, values) in &self.parameters {
+            if values.is_empty() {
+                return Err(anyhow!("No values to sweep for {}", field));
+            }
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination: Vec<(String, Value)>| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((field.clone(), value.clone()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+
+        let seeds = if self.seeds.is_empty() {
+            vec![base.seed]
+        } else {
+            self.seeds.iter().copied().map(Some).collect()
+        };
+
+        let mut runs = Vec::new();
+        for parameters in combinations {
+            let mut conf = base.clone();
+            for (field, value) in &parameters {
+                conf.set(field, value.clone())?;
+            }
+            if let Some(max) = self.max_generations {
+                conf.end.push(EndCondition::MaxGenerations(max));
+            }
+            if let Some(secs) = self.timeout {
+                conf.end.push(EndCondition::WallClock(secs));
+            }
+            // every combination is checked before running any of them
+            let cannot_sweep = |e| {
+                let parameters: Vec<_> = parameters
+                    .iter()
+                    .map(|(field, value)| format!("{}={}", field, value))
+                    .collect();
+                anyhow!("Cannot sweep {}: {}", parameters.join(", "), e)
+            };
+            conf.validate().map_err(cannot_sweep)?;
+            if conf.is_endless() {
+                return Err(cannot_sweep(anyhow!("the runs would never end")));
+            }
+            for &seed in &seeds {
+                let mut conf = conf.clone();
+                conf.seed = seed;
+                runs.push(Run {
+                    parameters: parameters.clone(),
+                    conf,
+                });
+            }
+        }
+        Ok(runs)
+    }
+
+    /// Runs each combination of the grid, one after the other, reporting the
+    /// outcome of each as soon as the run is over.
+    pub fn run(
+        &self,
+        base: &Conf,
+        mut report: impl FnMut(&Outcome) -> Result<()>,
+    ) -> Result<()> {
+        let runs = self.runs(base)?;
+        for (i, run) in runs.into_iter().enumerate() {
+            log::info!("Sweep run {}: {:?}", i, run.parameters);
+            let context = Context::new(run.conf.with_map()?);
+            let mut state = State::new(Arc::new(context))?;
+            let summary = headless::run(&mut state)?;
+            report(&Outcome {
+                parameters: run.parameters,
+                summary,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Outcome {
+    /// Gets the columns of the row of this outcome: the swept fields come
+    /// first, followed by the summary fields.
+    pub fn columns(&self) -> Vec<(String, Value)> {
+        let summary = &self.summary;
+        let end = serde_json::to_value(summary.end).unwrap_or_default();
+        let colonies =
+            serde_json::to_value(&summary.colonies).unwrap_or_default();
+        self.parameters
+            .iter()
+            .cloned()
+            .chain([
+                ("seed".into(), summary.seed.into()),
+                ("generations".into(), summary.generations.into()),
+                ("collected".into(), summary.collected.into()),
+                ("colonies".into(), colonies),
+                ("total".into(), summary.total.into()),
+                ("completed".into(), summary.completed.into()),
+                ("end".into(), end),
+                ("wallTime".into(), summary.wall_time.into()),
+            ])
+            .collect()
+    }
+}
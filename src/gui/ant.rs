@@ -0,0 +1,55 @@
+use ggez::graphics;
+use semeion::*;
+
+use crate::entity::ant;
+use crate::{entity, game, gui};
+
+/// Draws an Ant, colored according to its colony and current activity.
+pub fn draw(
+    ctx: &mut ggez::Context,
+    mesh: &graphics::Mesh,
+    conf: &game::Conf,
+    location: Location,
+    activity: ant::Activity,
+    color: graphics::Color,
+    mut transform: Transform,
+) -> ggez::GameResult {
+    // translate according to the current entity location
+    transform *=
+        gui::translation(entity::Kind::Ant, location, conf.env.tile_side);
+
+    // the Ants carrying food are lighter than the ones searching for it
+    let color = match activity {
+        ant::Activity::Foraging => color,
+        ant::Activity::Carrying => graphics::Color::new(
+            (color.r + 1.0) / 2.0,
+            (color.g + 1.0) / 2.0,
+            (color.b + 1.0) / 2.0,
+            color.a,
+        ),
+    };
+
+    graphics::draw(
+        ctx,
+        mesh,
+        graphics::DrawParam::default()
+            .transform(transform.to_column_matrix4())
+            .color(color),
+    )
+}
+
+/// Constructs a new mesh for an Ant.
+pub fn mesh(
+    ctx: &mut ggez::Context,
+    conf: &game::Conf,
+) -> ggez::GameResult<graphics::Mesh> {
+    let color = graphics::Color::WHITE;
+    let entity_size = gui::size(entity::Kind::Ant, conf.env.tile_side);
+    let tolerance = 2.0;
+    let radius = entity_size / 2.0;
+    let center = [radius, radius];
+
+    let mut mesh = graphics::MeshBuilder::new();
+    mesh.circle(graphics::DrawMode::fill(), center, radius, tolerance, color)?;
+    mesh.build(ctx)
+}
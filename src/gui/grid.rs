@@ -0,0 +1,33 @@
+use ggez::graphics;
+use semeion::*;
+
+use crate::game;
+
+/// Constructs a new mesh for a grid of squared cells.
+pub fn mesh(
+    ctx: &mut ggez::Context,
+    conf: &game::Conf,
+) -> ggez::GameResult<graphics::Mesh> {
+    use ggez::mint::Point2;
+
+    let mut mesh = graphics::MeshBuilder::new();
+    let size = conf.size();
+    let stroke_width = 2.0;
+    let color = graphics::Color::BLACK;
+    let dimension: Dimension = conf.env.dimension.into();
+
+    // horizontal lines
+    for i in 0..=dimension.y {
+        let y = i as f32 * conf.env.tile_side;
+        let points = [Point2 { x: 0.0, y }, Point2 { x: size.width, y }];
+        mesh.line(&points, stroke_width, color)?;
+    }
+    // vertical lines
+    for i in 0..=dimension.x {
+        let x = i as f32 * conf.env.tile_side;
+        let points = [Point2 { x, y: 0.0 }, Point2 { x, y: size.height }];
+        mesh.line(&points, stroke_width, color)?;
+    }
+
+    mesh.build(ctx)
+}
@@ -0,0 +1,414 @@
+use ggez::event::{KeyCode, KeyMods};
+use ggez::{event, graphics, mint, timer};
+use semeion::*;
+use std::collections::HashMap;
+
+use crate::entity::Kind;
+use crate::{entity, game};
+
+pub use replay::*;
+
+mod ant;
+mod grid;
+mod morsel;
+mod nest;
+mod obstacle;
+mod phero;
+mod replay;
+
+/// The graphical interface used to watch the simulation.
+pub struct Viewer<'e> {
+    /// The state of the simulation being watched.
+    state: game::State<'e>,
+    /// The meshes used to draw the environment.
+    meshes: Meshes,
+    /// The summary of the simulation, once it's over.
+    summary: Option<game::Summary>,
+}
+
+/// The meshes used to draw the environment.
+struct Meshes {
+    /// The mesh of the grid of tiles.
+    grid: graphics::Mesh,
+    /// The mesh of the pheromones of any scent and colony.
+    phero: graphics::Mesh,
+    /// The map of entities meshes depending on their kind.
+    kinds: HashMap<Kind, graphics::Mesh>,
+    /// The color of each colony.
+    colors: Vec<graphics::Color>,
+}
+
+impl<'e> Viewer<'e> {
+    /// Constructs a new Viewer for the given game state, building the meshes
+    /// for each entity kind.
+    pub fn new(
+        mut state: game::State<'e>,
+        ctx: &mut ggez::Context,
+    ) -> ggez::GameResult<Self> {
+        // the metrics are always collected to be shown and exported
+        state.collect_metrics(None);
+        let meshes = Meshes::new(ctx, &state.context().conf)?;
+        Ok(Self {
+            state,
+            meshes,
+            summary: None,
+        })
+    }
+
+    /// Moves the simulation forward to the next generation, unless it's over,
+    /// and finishes it as soon as any of its end conditions is met.
+    fn step(&mut self) {
+        if self.summary.is_some() {
+            return;
+        }
+
+        let state = &mut self.state;
+        state.nextgen().expect("Cannot move to the next generation");
+        if state.is_simulation_over() {
+            let summary = game::Summary::new(state);
+            log::info!(
+                "Simulation over after {} generations",
+                summary.generations
+            );
+            if let Err(e) = state.finish() {
+                log::error!("Cannot finish simulation: {}", e);
+            }
+            self.summary = Some(summary);
+        }
+    }
+
+    /// Draws all the visible entities of the environment.
+    fn draw_entities(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let conf = &self.state.context().conf;
+        let transform = Transform::identity();
+        self.meshes.draw_grid(ctx, conf)?;
+        self.meshes
+            .draw_pheros(ctx, conf, &self.state.context().pheros())?;
+
+        for entity in self.state.env.entities() {
+            let kind = entity.kind();
+            if !conf.is_visible(&kind) {
+                continue;
+            }
+
+            let mesh = self.meshes.get(&kind);
+            let location = entity.location().expect("Invalid entity location");
+            match kind {
+                Kind::Nest { colony } => {
+                    nest::draw(ctx, mesh, conf, colony, location, transform)?
+                }
+                Kind::Morsel => {
+                    let state: &entity::morsel::State =
+                        entity::downcast_state(entity);
+                    morsel::draw(
+                        ctx,
+                        mesh,
+                        conf,
+                        location,
+                        state.storage(),
+                        transform,
+                    )?
+                }
+                Kind::Obstacle => {
+                    obstacle::draw(ctx, mesh, conf, location, transform)?
+                }
+                Kind::Ant => {
+                    let state: &entity::ant::State =
+                        entity::downcast_state(entity);
+                    let color = self.meshes.color(state.colony());
+                    ant::draw(
+                        ctx,
+                        mesh,
+                        conf,
+                        location,
+                        state.activity(),
+                        color,
+                        transform,
+                    )?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw simulation statistics.
+    fn draw_stats(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let mut text = format!(
+            "Collected: {}/{}",
+            self.state.delivered(),
+            self.state.total_storage()
+        );
+        text += &format!("\nGeneration: {}", self.state.generation());
+        if let Some(metrics) = self.state.metrics() {
+            let sample = metrics.latest();
+            text += &format!(
+                "\nForaging: {}\nCarrying: {}\nLost: {}",
+                sample.foraging, sample.carrying, sample.lost
+            );
+            if self.state.context().conf.ants.energy.is_some() {
+                text += &format!("\nDeaths: {}", sample.deaths);
+            }
+            if self.state.context().conf.nest.growth.is_some() {
+                text += &format!("\nBirths: {}", sample.births);
+            }
+            text += &format!(
+                "\nDiscovered: {}/{}",
+                metrics.discoveries().len(),
+                sample.morsels.len()
+            );
+            text += &colonies_text(&sample.colonies);
+        }
+        draw_text(ctx, text)
+    }
+
+    /// Draws the summary of the simulation in the middle of the window, once
+    /// the simulation is over.
+    fn draw_summary(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let summary = match &self.summary {
+            Some(summary) => summary,
+            None => return Ok(()),
+        };
+
+        let percentage = if summary.total > 0 {
+            summary.collected as f64 * 100.0 / summary.total as f64
+        } else {
+            100.0
+        };
+        let end = summary.end.map(|end| end.to_string()).unwrap_or_default();
+        let text = format!(
+            "Simulation over: {}\n\nGenerations: {}\nCollected: {}/{} \
+             ({:.1}%)\nWall time: {:.1}s\nSeed: {}\n\nPress Escape to quit",
+            end,
+            summary.generations,
+            summary.collected,
+            summary.total,
+            percentage,
+            summary.wall_time,
+            summary.seed
+        );
+        let text = text + &colonies_text(&summary.colonies);
+        let fragment =
+            graphics::TextFragment::new(text).color(graphics::Color::WHITE);
+        let text = graphics::Text::new(fragment);
+
+        // center the text on a dark panel
+        let margin = 20.0;
+        let bounds = text.dimensions(ctx);
+        let screen = graphics::screen_coordinates(ctx);
+        let x = (screen.w - bounds.w) / 2.0;
+        let y = (screen.h - bounds.h) / 2.0;
+        let panel = graphics::Rect::new(
+            x - margin,
+            y - margin,
+            bounds.w + margin * 2.0,
+            bounds.h + margin * 2.0,
+        );
+        let color = graphics::Color::from_rgba(0, 0, 0, 200);
+        let mesh = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            panel,
+            color,
+        )?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+
+        let dest = mint::Point2 { x, y };
+        graphics::draw(ctx, &text, graphics::DrawParam::default().dest(dest))
+    }
+}
+
+impl Meshes {
+    /// Builds the meshes for each entity kind.
+    fn new(
+        ctx: &mut ggez::Context,
+        conf: &game::Conf,
+    ) -> ggez::GameResult<Self> {
+        // initialize the meshes map for each entity kind
+        let mut meshes = HashMap::new();
+        meshes.insert(Kind::Morsel, morsel::mesh(ctx, conf)?);
+        meshes.insert(Kind::Ant, ant::mesh(ctx, conf)?);
+        meshes.insert(Kind::Obstacle, obstacle::mesh(ctx, conf)?);
+
+        let colors: Vec<_> = conf
+            .colonies()
+            .iter()
+            .map(|colony| graphics::Color::from(colony.color))
+            .collect();
+        for (colony, &color) in colors.iter().enumerate() {
+            let nest = nest::mesh(ctx, conf, colony, color)?;
+            meshes.insert(Kind::Nest { colony }, nest);
+        }
+
+        let grid = grid::mesh(ctx, conf)?;
+        let phero = phero::mesh(ctx, conf)?;
+        Ok(Self {
+            grid,
+            phero,
+            kinds: meshes,
+            colors,
+        })
+    }
+
+    /// Gets the color of the given colony.
+    fn color(&self, colony: entity::Colony) -> graphics::Color {
+        self.colors[colony]
+    }
+
+    /// Gets the graphics mesh associated with the given entity kind.
+    fn get(&self, kind: &Kind) -> &graphics::Mesh {
+        self.kinds
+            .get(kind)
+            .unwrap_or_else(|| panic!("Mesh not found for {:?}", kind))
+    }
+
+    /// Draws the grid of tiles, if visible.
+    fn draw_grid(
+        &self,
+        ctx: &mut ggez::Context,
+        conf: &game::Conf,
+    ) -> ggez::GameResult {
+        if conf.env.grid.visible {
+            graphics::draw(ctx, &self.grid, graphics::DrawParam::default())?;
+        }
+        Ok(())
+    }
+
+    /// Draws the pheromones of the given field which scent is visible.
+    fn draw_pheros(
+        &self,
+        ctx: &mut ggez::Context,
+        conf: &game::Conf,
+        pheros: &game::PheroField,
+    ) -> ggez::GameResult {
+        for ((scent, _, location), concentration) in pheros.pheros() {
+            if conf.pheromones.is_visible(scent) {
+                phero::draw(ctx, &self.phero, conf, location, concentration)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'e> event::EventHandler<ggez::GameError> for Viewer<'e> {
+    /// Updates the game state by moving the environment forward to the next
+    /// generation, until the simulation is over.
+    fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let target_fps = self.state.context().conf.fps;
+        if let Some(fps) = target_fps {
+            while timer::check_update_time(ctx, fps) {
+                self.step();
+            }
+        } else {
+            self.step();
+        }
+
+        Ok(())
+    }
+
+    /// Saves a snapshot of the current state of the simulation when the S key
+    /// is pressed, exports the metrics collected so far when the M key is
+    /// pressed, and quits when the Escape key is pressed.
+    fn key_down_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        keycode: KeyCode,
+        _: KeyMods,
+        repeat: bool,
+    ) {
+        if keycode == KeyCode::Escape {
+            event::quit(ctx);
+        }
+
+        if keycode == KeyCode::S && !repeat {
+            let path = format!("snapshot-{}.json", self.state.generation());
+            if let Err(e) = self.state.snapshot().save(&path) {
+                log::error!("Cannot save snapshot: {}", e);
+            }
+        }
+
+        if keycode == KeyCode::M && !repeat {
+            let path = format!("metrics-{}.csv", self.state.generation());
+            let metrics = self.state.metrics().map(|m| m.save(&path));
+            if let Some(Err(e)) = metrics {
+                log::error!("Cannot export metrics: {}", e);
+            }
+        }
+    }
+
+    /// Finishes the recording and the metrics of the simulation, if any,
+    /// before quitting.
+    fn quit_event(&mut self, _: &mut ggez::Context) -> bool {
+        // once over, the simulation has already been finished
+        if self.summary.is_some() {
+            return false;
+        }
+        if let Err(e) = self.state.finish() {
+            log::error!("Cannot finish simulation: {}", e);
+        }
+        false
+    }
+
+    /// Draws the environment with all its entities.
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let background = self.state.context().conf.env.background;
+        graphics::clear(ctx, background.into());
+
+        self.draw_entities(ctx)?;
+        self.draw_stats(ctx)?;
+        self.draw_summary(ctx)?;
+
+        graphics::present(ctx)?;
+        timer::yield_now();
+        Ok(())
+    }
+}
+
+/// Gets the size of the entity in number of pixels according to the given
+/// tile side.
+fn size(kind: Kind, side: f32) -> f32 {
+    match kind {
+        Kind::Nest { .. } => side + side * 0.1,
+        Kind::Morsel => side + side * 0.1,
+        Kind::Ant => side - side * 0.2,
+        Kind::Obstacle => side,
+    }
+}
+
+/// Gets the translation that moves the mesh of an entity of the given kind to
+/// the center of the tile with the given location.
+fn translation(kind: Kind, location: Location, side: f32) -> Transform {
+    // shift the center of the mesh to the center of the Tile
+    let entity_size = size(kind, side);
+    let center_offset = entity_size / 2.0 - side / 2.0;
+    let loc = location.to_pixel_coords(side) - center_offset;
+    Transform::translate(loc)
+}
+
+/// Gets the lines of text with the statistics of each colony, if there is
+/// more than a single colony.
+fn colonies_text(colonies: &[game::ColonyStats]) -> String {
+    if colonies.len() < 2 {
+        return String::new();
+    }
+    colonies
+        .iter()
+        .enumerate()
+        .map(|(i, colony)| {
+            format!(
+                "\nColony {}: {} collected, {} ants",
+                i, colony.delivered, colony.ants
+            )
+        })
+        .collect()
+}
+
+/// Draws the given text in the top left corner of the window.
+fn draw_text(ctx: &mut ggez::Context, text: String) -> ggez::GameResult {
+    let foreground = graphics::Color::WHITE;
+    let fragment = graphics::TextFragment::new(text).color(foreground);
+    let text = graphics::Text::new(fragment);
+
+    let dest = mint::Point2 { x: 10.0, y: 10.0 };
+    graphics::draw(ctx, &text, graphics::DrawParam::default().dest(dest))
+}
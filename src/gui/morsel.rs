@@ -0,0 +1,59 @@
+use ggez::graphics;
+use semeion::*;
+
+use crate::{entity, game, gui};
+
+/// Draws a Morsel, scaled according to the food it still stores.
+pub fn draw(
+    ctx: &mut ggez::Context,
+    mesh: &graphics::Mesh,
+    conf: &game::Conf,
+    location: Location,
+    storage: u64,
+    mut transform: Transform,
+) -> ggez::GameResult {
+    let kind = entity::Kind::Morsel;
+    let entity_size = gui::size(kind, conf.env.tile_side);
+    // translate according to the current entity location
+    let translation = gui::translation(kind, location, conf.env.tile_side);
+
+    // scale according to a value proportional to the remaining storage
+    let max_storage = conf.morsels.storage as f32;
+    let scale = (storage as f32 / max_storage).min(1.0);
+    let scale = Transform::scale_around(
+        [scale, scale],
+        [entity_size / 2.0, entity_size / 2.0],
+    );
+
+    transform *= translation * scale;
+
+    graphics::draw(
+        ctx,
+        mesh,
+        graphics::DrawParam::default().transform(transform.to_column_matrix4()),
+    )
+}
+
+/// Constructs a new mesh for a Morsel.
+pub fn mesh(
+    ctx: &mut ggez::Context,
+    conf: &game::Conf,
+) -> ggez::GameResult<graphics::Mesh> {
+    let mut mesh = graphics::MeshBuilder::new();
+    let color = graphics::Color::new(0.3, 0.5, 0.0, 1.0);
+    let entity_size = gui::size(entity::Kind::Morsel, conf.env.tile_side);
+
+    let outer = graphics::Rect::new(0.0, 0.0, entity_size, entity_size);
+    mesh.rectangle(graphics::DrawMode::stroke(1.0), outer, color)?;
+
+    let half_size = entity_size / 2.0;
+    let inner = graphics::Rect::new(
+        half_size / 2.0,
+        half_size / 2.0,
+        half_size,
+        half_size,
+    );
+    mesh.rectangle(graphics::DrawMode::fill(), inner, color)?;
+
+    mesh.build(ctx)
+}
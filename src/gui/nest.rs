@@ -0,0 +1,51 @@
+use ggez::graphics;
+use semeion::*;
+
+use crate::{entity, game, gui};
+
+/// Draws the Nest of the given colony.
+pub fn draw(
+    ctx: &mut ggez::Context,
+    mesh: &graphics::Mesh,
+    conf: &game::Conf,
+    colony: entity::Colony,
+    location: Location,
+    mut transform: Transform,
+) -> ggez::GameResult {
+    // translate according to the current entity location
+    let kind = entity::Kind::Nest { colony };
+    transform *= gui::translation(kind, location, conf.env.tile_side);
+
+    graphics::draw(
+        ctx,
+        mesh,
+        graphics::DrawParam::default().transform(transform.to_column_matrix4()),
+    )
+}
+
+/// Constructs a new mesh for the Nest of the given colony, drawn with the
+/// color of the colony.
+pub fn mesh(
+    ctx: &mut ggez::Context,
+    conf: &game::Conf,
+    colony: entity::Colony,
+    color: graphics::Color,
+) -> ggez::GameResult<graphics::Mesh> {
+    let mut mesh = graphics::MeshBuilder::new();
+
+    let kind = entity::Kind::Nest { colony };
+    let entity_size = gui::size(kind, conf.env.tile_side);
+    let outer = graphics::Rect::new(0.0, 0.0, entity_size, entity_size);
+    mesh.rectangle(graphics::DrawMode::stroke(3.0), outer, color)?;
+
+    let half_size = entity_size / 2.0;
+    let inner = graphics::Rect::new(
+        half_size / 2.0,
+        half_size / 2.0,
+        half_size,
+        half_size,
+    );
+    mesh.rectangle(graphics::DrawMode::fill(), inner, color)?;
+
+    mesh.build(ctx)
+}
@@ -0,0 +1,38 @@
+use ggez::graphics;
+use semeion::*;
+
+use crate::{entity, game, gui};
+
+/// Draws an Obstacle.
+pub fn draw(
+    ctx: &mut ggez::Context,
+    mesh: &graphics::Mesh,
+    conf: &game::Conf,
+    location: Location,
+    mut transform: Transform,
+) -> ggez::GameResult {
+    let kind = entity::Kind::Obstacle;
+    // translate according to the current entity location
+    transform *= gui::translation(kind, location, conf.env.tile_side);
+
+    graphics::draw(
+        ctx,
+        mesh,
+        graphics::DrawParam::default().transform(transform.to_column_matrix4()),
+    )
+}
+
+/// Constructs a new mesh for an Obstacle.
+pub fn mesh(
+    ctx: &mut ggez::Context,
+    conf: &game::Conf,
+) -> ggez::GameResult<graphics::Mesh> {
+    let mut mesh = graphics::MeshBuilder::new();
+    let color = graphics::Color::new(0.4, 0.4, 0.4, 1.0);
+    let entity_size = gui::size(entity::Kind::Obstacle, conf.env.tile_side);
+
+    let rect = graphics::Rect::new(0.0, 0.0, entity_size, entity_size);
+    mesh.rectangle(graphics::DrawMode::fill(), rect, color)?;
+
+    mesh.build(ctx)
+}
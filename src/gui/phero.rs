@@ -0,0 +1,59 @@
+use ggez::graphics;
+use semeion::*;
+
+use crate::game;
+
+/// Draws the pheromone of the tile with the given location, scaled and
+/// colored according to its concentration.
+pub fn draw(
+    ctx: &mut ggez::Context,
+    mesh: &graphics::Mesh,
+    conf: &game::Conf,
+    location: Location,
+    concentration: u64,
+) -> ggez::GameResult {
+    // the pheromone fills the whole tile
+    let entity_size = conf.env.tile_side;
+    let translation =
+        Transform::translate(location.to_pixel_coords(entity_size));
+
+    // scale according to a value proportional to the concentration left
+    let lifespan = concentration as f32;
+    let max_concentration = conf.ants.max_phero_concentration as f32;
+    let scale = (lifespan / max_concentration).min(0.5);
+    let scale = Transform::scale_around(
+        [scale, scale],
+        [entity_size / 2.0, entity_size / 2.0],
+    );
+
+    let transform = translation * scale;
+
+    // the brighter the entity the more concentration it represents, up to
+    // completely white (255, 255, 255)
+    let val = (lifespan * 0.1) as u8;
+    let color = graphics::Color::from_rgb(val, val, val);
+
+    graphics::draw(
+        ctx,
+        mesh,
+        graphics::DrawParam::default()
+            .color(color)
+            .transform(transform.to_column_matrix4()),
+    )
+}
+
+/// Constructs a new mesh for the pheromones, of any scent and colony.
+pub fn mesh(
+    ctx: &mut ggez::Context,
+    conf: &game::Conf,
+) -> ggez::GameResult<graphics::Mesh> {
+    let color = graphics::Color::WHITE;
+    let entity_size = conf.env.tile_side;
+    let tolerance = 0.5;
+    let radius = entity_size / 2.0;
+    let center = [radius, radius];
+
+    let mut mesh = graphics::MeshBuilder::new();
+    mesh.circle(graphics::DrawMode::fill(), center, radius, tolerance, color)?;
+    mesh.build(ctx)
+}
@@ -0,0 +1,186 @@
+use ggez::event::{KeyCode, KeyMods};
+use ggez::{event, graphics, timer};
+use semeion::*;
+
+use super::{ant, draw_text, morsel, nest, obstacle, Meshes};
+use crate::entity::Kind;
+use crate::game;
+
+/// The number of generations skipped when seeking.
+const SEEK_STEP: u64 = 100;
+
+/// The maximum number of generations replayed for each update.
+const MAX_SPEED: u64 = 64;
+
+/// The graphical interface used to watch a recorded simulation.
+pub struct Player {
+    /// The replay of the recorded simulation.
+    replay: game::Replay,
+    /// The meshes used to draw the environment.
+    meshes: Meshes,
+    /// Whether the playback is paused.
+    paused: bool,
+    /// The number of generations replayed for each update.
+    speed: u64,
+}
+
+impl Player {
+    /// Constructs a new Player for the given replay, building the meshes for
+    /// each entity kind.
+    pub fn new(
+        replay: game::Replay,
+        ctx: &mut ggez::Context,
+    ) -> ggez::GameResult<Self> {
+        let meshes = Meshes::new(ctx, replay.conf())?;
+        Ok(Self {
+            replay,
+            meshes,
+            paused: false,
+            speed: 1,
+        })
+    }
+
+    /// Draws all the visible entities of the current frame.
+    fn draw_entities(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let conf = self.replay.conf();
+        let frame = self.replay.frame();
+        let transform = Transform::identity();
+        self.meshes.draw_grid(ctx, conf)?;
+        self.meshes.draw_pheros(ctx, conf, &frame.pheros)?;
+
+        if conf.is_visible(&Kind::Obstacle) {
+            let mesh = self.meshes.get(&Kind::Obstacle);
+            for &location in &frame.obstacles {
+                obstacle::draw(ctx, mesh, conf, location, transform)?;
+            }
+        }
+
+        for (colony, &(location, _)) in frame.nests.iter().enumerate() {
+            let kind = Kind::Nest { colony };
+            if conf.is_visible(&kind) {
+                let mesh = self.meshes.get(&kind);
+                nest::draw(ctx, mesh, conf, colony, location, transform)?;
+            }
+        }
+
+        if conf.is_visible(&Kind::Morsel) {
+            let mesh = self.meshes.get(&Kind::Morsel);
+            for &(location, storage) in frame.morsels.values() {
+                morsel::draw(ctx, mesh, conf, location, storage, transform)?;
+            }
+        }
+
+        if conf.is_visible(&Kind::Ant) {
+            let mesh = self.meshes.get(&Kind::Ant);
+            for &(location, activity, colony) in frame.ants.values() {
+                let color = self.meshes.color(colony);
+                ant::draw(
+                    ctx, mesh, conf, location, activity, color, transform,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw replay statistics and playback status.
+    fn draw_stats(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let frame = self.replay.frame();
+        let mut text =
+            format!("Collected: {}/{}", frame.delivered, frame.total_storage());
+        text += &format!(
+            "\nGeneration: {}/{}",
+            frame.generation,
+            self.replay.last_generation()
+        );
+        if self.replay.conf().ants.energy.is_some() {
+            text += &format!("\nDeaths: {}", frame.deaths);
+        }
+        if self.replay.conf().nest.growth.is_some() {
+            text += &format!("\nBirths: {}", frame.births);
+        }
+        if frame.nests.len() > 1 {
+            for (colony, (_, delivered)) in frame.nests.iter().enumerate() {
+                text +=
+                    &format!("\nColony {}: {} collected", colony, delivered);
+            }
+        }
+        text += &format!("\nSpeed: x{}", self.speed);
+        if self.paused {
+            text += "\nPaused";
+        }
+        draw_text(ctx, text)
+    }
+}
+
+impl event::EventHandler<ggez::GameError> for Player {
+    /// Moves the replay forward according to the playback speed, unless paused.
+    fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let target_fps = self.replay.conf().fps;
+        let mut step = || {
+            if !self.paused {
+                for _ in 0..self.speed {
+                    if !self.replay.step() {
+                        break;
+                    }
+                }
+            }
+        };
+
+        if let Some(fps) = target_fps {
+            while timer::check_update_time(ctx, fps) {
+                step();
+            }
+        } else {
+            step();
+        }
+
+        Ok(())
+    }
+
+    /// Controls the playback: Space pauses or resumes it, Left and Right seek
+    /// backward and forward (ten times faster with Shift), Up and Down change
+    /// the playback speed, Home and End jump to the first and last generation,
+    /// and Escape quits.
+    fn key_down_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        _: bool,
+    ) {
+        let generation = self.replay.frame().generation;
+        let seek_step = if keymods.contains(KeyMods::SHIFT) {
+            SEEK_STEP * 10
+        } else {
+            SEEK_STEP
+        };
+
+        match keycode {
+            KeyCode::Space => self.paused = !self.paused,
+            KeyCode::Right => self.replay.seek(generation + seek_step),
+            KeyCode::Left => {
+                self.replay.seek(generation.saturating_sub(seek_step))
+            }
+            KeyCode::Up => self.speed = (self.speed * 2).min(MAX_SPEED),
+            KeyCode::Down => self.speed = (self.speed / 2).max(1),
+            KeyCode::Home => self.replay.seek(self.replay.first_generation()),
+            KeyCode::End => self.replay.seek(self.replay.last_generation()),
+            KeyCode::Escape => event::quit(ctx),
+            _ => (),
+        }
+    }
+
+    /// Draws the environment as it was in the current generation.
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let background = self.replay.conf().env.background;
+        graphics::clear(ctx, background.into());
+
+        self.draw_entities(ctx)?;
+        self.draw_stats(ctx)?;
+
+        graphics::present(ctx)?;
+        timer::yield_now();
+        Ok(())
+    }
+}
@@ -1,2 +1,15 @@
-mod entity;
+//! A basic ant colony simulation, built on top of the entity engine
+//! [semeion](https://github.com/gliderkite/semeion).
+//!
+//! The [`Simulation`] facade allows to drive simulations programmatically,
+//! while the [`game`] module contains the configuration, the headless runner,
+//! snapshots, recordings, metrics and sweeps, and the [`entity`] module the
+//! entities that populate the environment.
+
+pub use simulation::*;
+
+pub mod entity;
 pub mod game;
+#[cfg(feature = "gui")]
+pub mod gui;
+mod simulation;
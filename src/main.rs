@@ -1,33 +1,246 @@
-use anyhow::Result;
-use ggez::conf::{WindowMode, WindowSetup};
-use ggez::*;
-use std::{env, sync::Arc};
-
-mod entity;
-mod game;
+use anyhow::{anyhow, bail, Result};
+use formicarium::game;
+use std::{env, path::PathBuf, sync::Arc};
 
 /// The path of the file with the default game configuration.
 const GAME_CONFIG_PATH: &str = "conf.json";
 
+/// The command line arguments.
+#[derive(Debug, Default)]
+struct Args {
+    /// The path of the game configuration file.
+    conf_path: Option<String>,
+    /// The path of the grid of values to sweep, when running the `sweep`
+    /// command.
+    sweep: Option<PathBuf>,
+    /// The path of the file the configuration is written to, when running the
+    /// `convert` command.
+    convert: Option<PathBuf>,
+    /// The path of the file where the outcomes of a sweep are written.
+    output: Option<PathBuf>,
+    /// Whether the simulation should run without any window.
+    headless: bool,
+    /// The maximum number of generations simulated, in addition to the end
+    /// conditions of the configuration.
+    max_generations: Option<u64>,
+    /// The path of the snapshot the simulation is restored from.
+    load: Option<PathBuf>,
+    /// The path where the snapshot of the final state is saved in headless
+    /// mode.
+    save: Option<PathBuf>,
+    /// The path where the events of the simulation are recorded.
+    record: Option<PathBuf>,
+    /// The path where the metrics of the simulation are exported.
+    metrics: Option<PathBuf>,
+    /// The path of the recording to replay instead of running a simulation.
+    replay: Option<PathBuf>,
+    /// Whether an invalid configuration should stop the program rather than
+    /// being replaced by the default one.
+    strict: bool,
+    /// The assignments applied to the configuration after reading it.
+    overrides: Vec<String>,
+    /// Whether the effective configuration should be printed instead of
+    /// running the simulation.
+    print_conf: bool,
+}
+
+impl Args {
+    /// Parses the command line arguments.
+    fn parse() -> Result<Self> {
+        let mut args = Self::default();
+        let mut positional = Vec::new();
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| anyhow!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--headless" => args.headless = true,
+                "--strict" => args.strict = true,
+                "--set" => args.overrides.push(value()?),
+                "--print-conf" => args.print_conf = true,
+                "--max-generations" => {
+                    args.max_generations = Some(value()?.parse()?)
+                }
+                "--load" => args.load = Some(value()?.into()),
+                "--save" => args.save = Some(value()?.into()),
+                "--record" => args.record = Some(value()?.into()),
+                "--replay" => args.replay = Some(value()?.into()),
+                "--output" => args.output = Some(value()?.into()),
+                "--metrics" => args.metrics = Some(value()?.into()),
+                _ if arg.starts_with("--") => bail!("Unknown option {}", arg),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let mut next = positional.next();
+        if next.as_deref() == Some("sweep") {
+            let grid = positional
+                .next()
+                .ok_or_else(|| anyhow!("Missing sweep grid path"))?;
+            args.sweep = Some(grid.into());
+            next = positional.next();
+        } else if next.as_deref() == Some("convert") {
+            next = positional.next();
+            let output = positional.next().ok_or_else(|| {
+                anyhow!("Missing converted configuration path")
+            })?;
+            args.convert = Some(output.into());
+        }
+        args.conf_path = next;
+        if let Some(arg) = positional.next() {
+            bail!("Unexpected argument {}", arg);
+        }
+        Ok(args)
+    }
+
+    /// Applies the overrides given on the command line, if any, to the given
+    /// configuration, and adds the limits to its end conditions.
+    fn customize(&self, conf: &mut game::Conf) -> Result<()> {
+        for assignment in &self.overrides {
+            conf.assign(assignment)?;
+        }
+        if let Some(max) = self.max_generations {
+            conf.end.push(game::EndCondition::MaxGenerations(max));
+        }
+        Ok(())
+    }
+}
+
 fn main() -> Result<()> {
     env_logger::init();
 
-    let conf_path = env::args()
-        .nth(1)
-        .unwrap_or_else(|| GAME_CONFIG_PATH.to_string());
-    let conf = game::Conf::parse(conf_path)
-        .map_err(|e| log::warn!("Using default configuration: {}", e))
-        .unwrap_or_default();
+    let args = Args::parse()?;
+    if let Some(path) = &args.convert {
+        let conf_path = args.conf_path.as_deref().unwrap_or(GAME_CONFIG_PATH);
+        let mut conf = game::Conf::parse(conf_path)?;
+        args.customize(&mut conf)?;
+        conf.validate()?;
+        return conf.save(path);
+    }
+
+    if let Some(path) = &args.replay {
+        let replay = game::Replay::new(game::Recording::load(path)?);
+        #[cfg(feature = "gui")]
+        if !args.headless {
+            return run_replay(replay);
+        }
+        drop(replay);
+        bail!("Recordings can only be replayed in the graphical interface");
+    }
+
+    let mut state = if let Some(path) = &args.load {
+        let mut snapshot = game::Snapshot::load(path)?;
+        args.customize(&mut snapshot.conf)?;
+        game::State::restore(snapshot)?
+    } else {
+        let conf = load_conf(&args)?;
+        if let Some(path) = &args.sweep {
+            return run_sweep(path, &conf, args.output.as_ref());
+        }
+        game::State::new(Arc::new(game::Context::new(conf.with_map()?)))?
+    };
+    if args.print_conf {
+        // the configuration includes the seed, even when randomly generated
+        let conf = &state.context().conf;
+        println!("{}", serde_json::to_string_pretty(conf)?);
+        return Ok(());
+    }
+    if let Some(path) = &args.record {
+        state.record(path)?;
+    }
+    if let Some(path) = &args.metrics {
+        state.collect_metrics(Some(path.clone()));
+    }
+
+    if args.headless || cfg!(not(feature = "gui")) {
+        let summary = game::headless::run(&mut state)?;
+        state.finish()?;
+        if let Some(path) = &args.save {
+            state.snapshot().save(path)?;
+        }
+        println!("{}", serde_json::to_string(&summary)?);
+        return Ok(());
+    }
+
+    #[cfg(feature = "gui")]
+    run_gui(state)?;
+    Ok(())
+}
+
+/// Parses and validates the game configuration given on the command line,
+/// falling back to the default configuration unless in strict mode.
+fn load_conf(args: &Args) -> Result<game::Conf> {
+    let conf_path = args.conf_path.as_deref().unwrap_or(GAME_CONFIG_PATH);
+    let conf = game::Conf::parse(conf_path).and_then(|mut conf| {
+        args.customize(&mut conf)?;
+        conf.validate().map(|_| conf)
+    });
+    match conf {
+        Err(e) if !args.strict => {
+            log::warn!("Using default configuration: {}", e);
+            let mut conf = game::Conf::default();
+            args.customize(&mut conf)?;
+            Ok(conf)
+        }
+        conf => conf,
+    }
+}
+
+/// Runs a headless simulation for each combination of the values of the grid
+/// with the given path, writing the outcomes to the given output file (or to
+/// the standard output as CSV).
+fn run_sweep(
+    path: &PathBuf,
+    conf: &game::Conf,
+    output: Option<&PathBuf>,
+) -> Result<()> {
+    let grid = game::sweep::Grid::load(path)?;
+    let mut writer = match output {
+        Some(path) => game::export::Writer::create(path)?,
+        None => game::export::Writer::stdout(game::export::Format::Csv),
+    };
+    grid.run(conf, |outcome| {
+        writer.write(outcome.columns())?;
+        // flush each row, so that the outcome of the completed runs is not
+        // lost if the sweep is interrupted
+        writer.flush()
+    })
+}
+
+/// Runs the simulation in a new window.
+#[cfg(feature = "gui")]
+fn run_gui(state: game::State<'static>) -> Result<()> {
+    let (mut ctx, events_loop) = window(&state.context().conf)?;
+    let viewer = formicarium::gui::Viewer::new(state, &mut ctx)?;
+    log::info!("Running game loop..");
+    ggez::event::run(ctx, events_loop, viewer)
+}
+
+/// Replays a recorded simulation in a new window.
+#[cfg(feature = "gui")]
+fn run_replay(replay: game::Replay) -> Result<()> {
+    let (mut ctx, events_loop) = window(replay.conf())?;
+    let player = formicarium::gui::Player::new(replay, &mut ctx)?;
+    log::info!("Running replay loop..");
+    ggez::event::run(ctx, events_loop, player)
+}
+
+/// Builds the window where the environment with the given configuration is
+/// drawn.
+#[cfg(feature = "gui")]
+fn window(
+    conf: &game::Conf,
+) -> Result<(ggez::Context, ggez::event::EventLoop<()>)> {
+    use ggez::conf::{WindowMode, WindowSetup};
 
     log::info!("Building game context");
     let (width, height) = conf.size().into();
-    let (mut ctx, events_loop) = ContextBuilder::new("ants", "Marco Conte")
+    let window = ggez::ContextBuilder::new("ants", "Marco Conte")
         .window_setup(WindowSetup::default().title("Formicarium!"))
         .window_mode(WindowMode::default().dimensions(width, height))
         .build()?;
-
-    let context = game::Context::with_context(conf, &mut ctx)?;
-    let state = game::State::new(Arc::new(context))?;
-    log::info!("Running game loop..");
-    event::run(ctx, events_loop, state)
+    Ok(window)
 }
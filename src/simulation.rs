@@ -0,0 +1,373 @@
+use anyhow::{anyhow, Result};
+use semeion::*;
+use std::sync::Arc;
+
+use crate::entity::{
+    self, ant, downcast_state, downcast_state_mut, morsel, nest, phero, Kind,
+};
+use crate::game;
+
+/// An Ant as seen from outside the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AntView {
+    /// The unique ID of the Ant.
+    pub id: Id,
+    /// The location of the Ant.
+    pub location: (i32, i32),
+    /// What the Ant is currently doing.
+    pub activity: ant::Activity,
+    /// The colony the Ant belongs to.
+    pub colony: entity::Colony,
+    /// The energy left to the Ant, if it needs to eat to live.
+    pub energy: Option<u64>,
+}
+
+/// A colony as seen from outside the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColonyView {
+    /// The index of the colony in the configuration.
+    pub colony: entity::Colony,
+    /// The location of the Nest of the colony.
+    pub location: (i32, i32),
+    /// The amount of food currently stored in the Nest of the colony.
+    pub storage: u64,
+    /// The total amount of food delivered to the Nest of the colony so far.
+    pub delivered: u64,
+    /// The number of Ants of the colony.
+    pub ants: usize,
+    /// The number of Ants of the colony that died of hunger.
+    pub deaths: u64,
+    /// The number of Ants of the colony spawned by its Nest.
+    pub births: u64,
+}
+
+/// A Morsel as seen from outside the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MorselView {
+    /// The unique ID of the Morsel.
+    pub id: Id,
+    /// The location of the Morsel.
+    pub location: (i32, i32),
+    /// The amount of food left in the Morsel.
+    pub storage: u64,
+}
+
+/// A running simulation of an ant colony.
+///
+/// This is the entry point to drive simulations from other crates: it allows
+/// to advance the simulation one generation at a time, to query the state of
+/// its entities, and to alter the environment between generations.
+///
+/// ```
+/// use formicarium::{game::Conf, Simulation};
+///
+/// let mut simulation = Simulation::new(Conf::default()).unwrap();
+/// simulation.run_until(|s| s.generation() == 100).unwrap();
+/// let carrying = simulation
+///     .ants()
+///     .filter(|ant| ant.activity == formicarium::entity::Activity::Carrying)
+///     .count();
+/// println!("{} ants are carrying food", carrying);
+/// ```
+pub struct Simulation {
+    state: game::State<'static>,
+}
+
+impl Simulation {
+    /// Constructs a new simulation with the given configuration, populating
+    /// the environment with its initial entities, laid out according to the
+    /// map of the configuration, if any.
+    pub fn new(conf: game::Conf) -> Result<Self> {
+        let context = game::Context::new(conf.with_map()?);
+        let state = game::State::new(Arc::new(context))?;
+        Ok(Self { state })
+    }
+
+    /// Restores the simulation recorded in the given snapshot.
+    pub fn restore(snapshot: game::Snapshot) -> Result<Self> {
+        let state = game::State::restore(snapshot)?;
+        Ok(Self { state })
+    }
+
+    /// Takes a snapshot of the current state of the simulation.
+    pub fn snapshot(&self) -> game::Snapshot {
+        self.state.snapshot()
+    }
+
+    /// Gets the underlying game state.
+    pub fn state(&self) -> &game::State<'static> {
+        &self.state
+    }
+
+    /// Gets the configuration of the simulation, including its seed.
+    pub fn conf(&self) -> &game::Conf {
+        &self.state.context().conf
+    }
+
+    /// Gets the current generation.
+    pub fn generation(&self) -> u64 {
+        self.state.generation()
+    }
+
+    /// Returns true only if any of the end conditions of the configuration is
+    /// met.
+    pub fn is_over(&self) -> bool {
+        self.state.is_simulation_over()
+    }
+
+    /// Gets the summary of the simulation as of the current generation.
+    pub fn summary(&self) -> game::Summary {
+        game::Summary::new(&self.state)
+    }
+
+    /// Moves the simulation forward to the next generation.
+    pub fn step(&mut self) -> Result<()> {
+        self.state.nextgen()
+    }
+
+    /// Moves the simulation forward until the given condition is satisfied,
+    /// which is checked before each generation, and returns the number of
+    /// generations simulated.
+    pub fn run_until(
+        &mut self,
+        mut condition: impl FnMut(&Self) -> bool,
+    ) -> Result<u64> {
+        let first = self.generation();
+        while !condition(self) {
+            self.step()?;
+        }
+        Ok(self.generation() - first)
+    }
+
+    /// Moves the simulation forward until any of the end conditions of the
+    /// configuration is met, and returns the summary of the run.
+    pub fn run(&mut self) -> Result<game::Summary> {
+        game::headless::run(&mut self.state)
+    }
+
+    /// Gets all the Ants in the environment.
+    pub fn ants(&self) -> impl Iterator<Item = AntView> + '_ {
+        self.state
+            .env
+            .entities()
+            .filter(|e| e.kind() == Kind::Ant)
+            .map(|e| {
+                let state = downcast_state::<ant::State>(e);
+                AntView {
+                    id: e.id(),
+                    location: location_of(e),
+                    activity: state.activity(),
+                    colony: state.colony(),
+                    energy: state.energy(),
+                }
+            })
+    }
+
+    /// Gets the overall strength of the pheromones with the given scent, left
+    /// by the Ants of any colony, at the given location, zero if there is
+    /// none.
+    pub fn phero_strength(
+        &self,
+        scent: phero::Scent,
+        location: (i32, i32),
+    ) -> u64 {
+        let pheros = self.state.context.pheros();
+        (0..self.state.context.conf.colonies().len())
+            .map(|colony| pheros.get(scent, colony, location))
+            .sum()
+    }
+
+    /// Gets the strength of the pheromone with the given scent, left by the
+    /// Ants of the given colony, at the given location, zero if there is none.
+    pub fn colony_phero_strength(
+        &self,
+        colony: entity::Colony,
+        scent: phero::Scent,
+        location: (i32, i32),
+    ) -> u64 {
+        self.state.context.pheros().get(scent, colony, location)
+    }
+
+    /// Gets all the colonies.
+    pub fn colonies(&self) -> impl Iterator<Item = ColonyView> + '_ {
+        let storages = self.state.colony_storages();
+        self.state
+            .colonies()
+            .into_iter()
+            .zip(storages)
+            .enumerate()
+            .map(move |(colony, (stats, storage))| ColonyView {
+                colony,
+                location: location_of(self.nest(colony)),
+                storage,
+                delivered: stats.delivered,
+                ants: stats.ants,
+                deaths: stats.deaths,
+                births: stats.births,
+            })
+    }
+
+    /// Gets the location of the Nest of the first colony.
+    pub fn nest_location(&self) -> (i32, i32) {
+        location_of(self.nest(0))
+    }
+
+    /// Gets the amount of food stored in the Nests of all the colonies.
+    pub fn nest_storage(&self) -> u64 {
+        self.state.storage()
+    }
+
+    /// Gets all the Morsels that still contain food.
+    pub fn morsels(&self) -> impl Iterator<Item = MorselView> + '_ {
+        self.state
+            .env
+            .entities()
+            .filter(|e| e.kind() == Kind::Morsel)
+            .map(|e| MorselView {
+                id: e.id(),
+                location: location_of(e),
+                storage: downcast_state::<morsel::State>(e).storage(),
+            })
+            .filter(|morsel| morsel.storage > 0)
+    }
+
+    /// Gets the total amount of food left in the Morsels.
+    pub fn remaining_food(&self) -> u64 {
+        self.morsels().map(|morsel| morsel.storage).sum()
+    }
+
+    /// Gets the locations of all the obstacles.
+    pub fn obstacles(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.state
+            .env
+            .entities()
+            .filter(|e| e.kind() == Kind::Obstacle)
+            .map(location_of)
+    }
+
+    /// Adds a new Morsel with the given storage of food at the given location,
+    /// and returns its ID.
+    pub fn add_morsel(
+        &mut self,
+        location: (i32, i32),
+        storage: u64,
+    ) -> Result<Id> {
+        self.check_location(location)?;
+        if storage == 0 {
+            return Err(anyhow!("A Morsel must store some food"));
+        }
+
+        let context = Arc::clone(&self.state.context);
+        let morsel =
+            entity::Morsel::new(location, storage, Arc::clone(&context));
+        let id = morsel.id();
+        self.state.env.insert(morsel);
+        context.record(game::Event::AddMorsel {
+            morsel: id,
+            location: location.into(),
+            storage,
+        });
+        Ok(id)
+    }
+
+    /// Removes the Morsel with the given ID, together with all the food it
+    /// stores. The Morsel is removed from the environment in the next
+    /// generation, until then it's found empty.
+    pub fn remove_morsel(&mut self, id: Id) -> Result<()> {
+        let morsel = self
+            .state
+            .env
+            .entities_mut()
+            .find(|e| e.kind() == Kind::Morsel && e.id() == id)
+            .ok_or_else(|| anyhow!("Morsel {} not found", id))?;
+        downcast_state_mut::<morsel::State>(morsel).remove();
+        self.state
+            .context
+            .record(game::Event::RemoveMorsel { morsel: id });
+        Ok(())
+    }
+
+    /// Moves the Nest of the first colony to the given location in the next
+    /// generation.
+    pub fn move_nest(&mut self, location: (i32, i32)) -> Result<()> {
+        self.move_colony_nest(0, location)
+    }
+
+    /// Moves the Nest of the given colony to the given location in the next
+    /// generation, which is from then on the location all the Ants of the
+    /// colony bring the food back to.
+    pub fn move_colony_nest(
+        &mut self,
+        colony: entity::Colony,
+        location: (i32, i32),
+    ) -> Result<()> {
+        self.check_location(location)?;
+        let colonies = self.conf().colonies().len();
+        if colony >= colonies {
+            return Err(anyhow!(
+                "{} is not one of the {} colonies",
+                colony,
+                colonies
+            ));
+        }
+
+        for e in self.state.env.entities_mut() {
+            match e.kind() {
+                Kind::Nest { colony: c } if c == colony => {
+                    downcast_state_mut::<nest::State>(e).relocate(location)
+                }
+                Kind::Ant => {
+                    let state = downcast_state_mut::<ant::State>(e);
+                    if state.colony() == colony {
+                        state.set_nest_location(location);
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.state.context.record(game::Event::MoveNest {
+            colony,
+            to: location.into(),
+        });
+        Ok(())
+    }
+
+    /// Gets the Nest entity of the given colony.
+    fn nest(
+        &self,
+        colony: entity::Colony,
+    ) -> &EntityTrait<'static, Kind, entity::Context> {
+        self.state
+            .env
+            .entities()
+            .find(|e| e.kind() == Kind::Nest { colony })
+            .expect("Cannot find the Nest")
+    }
+
+    /// Returns an error if the given location is outside the environment, or
+    /// on an obstacle.
+    fn check_location(&self, location: (i32, i32)) -> Result<()> {
+        let (width, height) = self.conf().env.dimension;
+        if location.0 < 0
+            || location.1 < 0
+            || location.0 >= width
+            || location.1 >= height
+        {
+            return Err(anyhow!(
+                "{:?} is outside {}x{}",
+                location,
+                width,
+                height
+            ));
+        }
+        if self.obstacles().any(|obstacle| obstacle == location) {
+            return Err(anyhow!("{:?} is on an obstacle", location));
+        }
+        Ok(())
+    }
+}
+
+/// Gets the location of the given Entity.
+fn location_of(e: &EntityTrait<Kind, entity::Context>) -> (i32, i32) {
+    e.location().expect("Invalid entity location").into()
+}
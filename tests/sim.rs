@@ -5,33 +5,1226 @@ use std::sync::Arc;
 const DEFAULT_CONFIG_PATH: &str = "tests/conf.json";
 
 /// Maximum number of generations before terminating the simulation for timeout.
-const MAX_GENERATIONS_COUNT: u64 = 150000;
+const MAX_GENERATIONS_COUNT: u64 = 2000;
+
+/// Gets the storage of each Morsel in the environment, including the empty
+/// ones that regrow their food.
+fn morsel_storages(state: &game::State) -> Vec<u64> {
+    let entities = state.snapshot().entities.into_iter();
+    entities
+        .filter_map(|e| match e {
+            game::EntitySnapshot::Morsel { storage, .. } => Some(storage),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Records the simulation with the given state for as long as the given
+/// condition holds, checked before each generation, and replays the recording
+/// up to the last generation, checking that it ends up in the same state.
+fn record_and_replay(
+    state: &mut game::State,
+    name: &str,
+    mut running: impl FnMut(&game::State) -> bool,
+) -> game::Replay {
+    let path = std::env::temp_dir().join(format!(
+        "formicarium-{}-{}.jsonl",
+        name,
+        std::process::id()
+    ));
+    state.record(&path).unwrap();
+    while running(state) {
+        state.nextgen().unwrap();
+    }
+    state.stop_recording().unwrap();
+
+    let recording = game::Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let mut replay = game::Replay::new(recording);
+    assert_eq!(replay.last_generation(), state.generation());
+    replay.seek(state.generation());
+
+    let expected = game::Frame::new(&state.snapshot());
+    let frame = replay.frame();
+    assert_eq!(frame.generation, expected.generation);
+    assert_eq!(frame.nests, expected.nests);
+    assert_eq!(frame.delivered, expected.delivered);
+    assert_eq!(frame.ants, expected.ants);
+    assert_eq!(frame.morsels, expected.morsels);
+    assert_eq!(frame.pheros, expected.pheros);
+    assert_eq!(frame.deaths, expected.deaths);
+    assert_eq!(frame.births, expected.births);
+    replay
+}
 
 #[test]
 fn run_simulation() {
     let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
 
-    for count in (10..=150).step_by(5) {
+    for count in (10..=150).step_by(35) {
         conf.ants.count = count;
 
         let context = game::Context::new(conf.clone());
         let mut state = game::State::new(Arc::new(context)).unwrap();
 
         while !state.is_simulation_over() {
-            let generation = state.env.nextgen().unwrap();
-            if generation > MAX_GENERATIONS_COUNT {
+            state.nextgen().unwrap();
+            if state.generation() > MAX_GENERATIONS_COUNT {
                 break;
             }
         }
 
-        if state.env.generation() > MAX_GENERATIONS_COUNT {
+        if state.env().generation() > MAX_GENERATIONS_COUNT {
             eprintln!("Timeout with {} ants!", count);
         } else {
             println!(
                 "Simulation over after {} generations with {} ants",
-                state.env.generation(),
+                state.env().generation(),
                 count
             );
         }
+
+        // the Ants find the food, and bring it to the Nest
+        let summary = game::Summary::new(&state);
+        assert!(summary.collected > 0);
+        assert_eq!(summary.total, conf.total_storage());
+        assert_eq!(summary.completed, state.is_simulation_over());
+    }
+}
+
+#[test]
+fn deterministic_simulation() {
+    // competing colonies of hungry Ants, with several Morsels stacked in the
+    // same tiles, so that the outcome would change with the scheduling of the
+    // Ants or with the order of the entities in a tile, if it depended on it
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(42);
+    conf.env.dimension = (40, 40);
+    conf.assign(
+        r#"colonies=[
+            {"location": [10, 10], "count": 30},
+            {"location": [30, 30], "count": 30}
+        ]"#,
+    )
+    .unwrap();
+    conf.assign(r#"ants.energy={"capacity": 150, "hunger": 0.5}"#)
+        .unwrap();
+    conf.morsels.count = 10;
+    conf.morsels.placed = [((20, 20), 5), ((20, 20), 10), ((20, 20), 15)]
+        .into_iter()
+        .chain([((12, 28), 8), ((12, 28), 8)])
+        .map(|(location, storage)| game::PlacedMorsel { location, storage })
+        .collect();
+
+    let run = || {
+        let path = std::env::temp_dir().join(format!(
+            "formicarium-deterministic-{}.jsonl",
+            std::process::id()
+        ));
+        let context = game::Context::new(conf.clone());
+        let mut state = game::State::new(Arc::new(context)).unwrap();
+        state.record(&path).unwrap();
+        for _ in 0..150 {
+            state.nextgen().unwrap();
+        }
+        state.stop_recording().unwrap();
+        let recording = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let colonies: Vec<_> = state
+            .colonies()
+            .iter()
+            .map(|c| (c.delivered, c.ants, c.deaths))
+            .collect();
+        let ants: i32 = state
+            .env()
+            .entities()
+            .filter(|e| e.kind() == entity::Kind::Ant)
+            .filter_map(|e| e.location())
+            .map(|location| location.x + location.y * 40)
+            .sum();
+        ((colonies, morsel_storages(&state), ants), recording)
+    };
+
+    // the outcome of the seed is the one of every previous run, on any machine
+    let (outcome, recording) = run();
+    let colonies = vec![(197, 26, 4), (89, 27, 3)];
+    let morsels = vec![13, 24, 38, 2, 50, 46, 2, 10, 15, 3];
+    assert_eq!(outcome, (colonies, morsels, 35063));
+    assert!(recording == run().1);
+}
+
+#[test]
+fn restore_snapshot() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(7);
+
+    let locations = |state: &game::State| {
+        state
+            .env()
+            .entities()
+            .filter_map(|e| e.location())
+            .map(<(i32, i32)>::from)
+            .collect::<Vec<_>>()
+    };
+
+    let context = game::Context::new(conf);
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    for _ in 0..100 {
+        state.nextgen().unwrap();
+    }
+
+    let snapshot = serde_json::to_string(&state.snapshot()).unwrap();
+    let snapshot = serde_json::from_str(&snapshot).unwrap();
+    let mut restored = game::State::restore(snapshot).unwrap();
+    assert_eq!(restored.generation(), state.generation());
+    assert_eq!(locations(&restored), locations(&state));
+    assert_eq!(restored.snapshot().pheromones, state.snapshot().pheromones);
+
+    for _ in 0..100 {
+        state.nextgen().unwrap();
+        restored.nextgen().unwrap();
+    }
+    assert_eq!(restored.generation(), state.generation());
+    assert_eq!(locations(&restored), locations(&state));
+    assert_eq!(restored.snapshot().pheromones, state.snapshot().pheromones);
+}
+
+#[test]
+fn replay_recording() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(11);
+    conf.ants.count = 20;
+
+    let context = game::Context::new(conf);
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    let mut replay =
+        record_and_replay(&mut state, "replay", |s| s.generation() < 1000);
+
+    // seek back and forth to go through the keyframes
+    replay.seek(state.generation() / 3);
+    replay.seek(state.generation());
+
+    let expected = game::Frame::new(&state.snapshot());
+    let frame = replay.frame();
+    assert_eq!(frame.generation, expected.generation);
+    assert_eq!(frame.delivered, expected.delivered);
+    assert_eq!(frame.ants, expected.ants);
+    assert_eq!(frame.morsels, expected.morsels);
+    assert_eq!(frame.pheros, expected.pheros);
+}
+
+#[test]
+fn sweep_grid() {
+    let conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    let grid: game::sweep::Grid = serde_json::from_str(
+        r#"{
+            "parameters": {
+                "ants.count": [5, 10],
+                "env.dimension": [[60, 60], [80, 80]]
+            },
+            "seeds": [1, 2],
+            "maxGenerations": 200
+        }"#,
+    )
+    .unwrap();
+
+    let mut outcomes = Vec::new();
+    grid.run(&conf, |outcome| {
+        outcomes.push(outcome.clone());
+        Ok(())
+    })
+    .unwrap();
+
+    assert_eq!(outcomes.len(), 8);
+    for (i, outcome) in outcomes.iter().enumerate() {
+        let count = outcome.parameters[0].1.as_u64().unwrap();
+        assert_eq!(count, [5, 10][i / 4]);
+        assert_eq!(outcome.summary.seed, [1, 2][i % 2]);
+        assert!(outcome.summary.generations <= 200);
+    }
+
+    let grid: game::sweep::Grid =
+        serde_json::from_str(r#"{"parameters": {"ants.cont": [5]}}"#).unwrap();
+    assert!(grid.runs(&conf).is_err());
+
+    // no run starts if any combination is invalid, such as an environment
+    // that doesn't contain the Nest
+    let grid: game::sweep::Grid = serde_json::from_str(
+        r#"{"parameters": {"env.dimension": [[60, 60], [20, 20]]}}"#,
+    )
+    .unwrap();
+    let mut runs = 0;
+    let result = grid.run(&conf, |_| {
+        runs += 1;
+        Ok(())
+    });
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("env.dimension=[20,20]"));
+    assert_eq!(runs, 0);
+
+    // the fields of the optional sections can be swept too
+    let grid: game::sweep::Grid = serde_json::from_str(
+        r#"{"parameters": {"nest.growth.foodPerAnt": [5, 10]}}"#,
+    )
+    .unwrap();
+    let runs = grid.runs(&conf).unwrap();
+    let growth = runs[1].conf.nest.growth.unwrap();
+    assert_eq!(growth.food_per_ant, 10);
+}
+
+#[test]
+fn collect_metrics() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(3);
+
+    let context = game::Context::new(conf.clone());
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    state.collect_metrics(None);
+    for _ in 0..300 {
+        state.nextgen().unwrap();
+    }
+
+    let metrics = state.metrics().unwrap();
+    assert_eq!(metrics.samples().len(), 301);
+    for (generation, sample) in metrics.samples().iter().enumerate() {
+        assert_eq!(sample.generation, generation as u64);
+        assert_eq!(sample.foraging + sample.carrying, conf.ants.count);
+        assert!(sample.lost <= conf.ants.count);
+        assert_eq!(sample.morsels.len(), conf.morsels.count);
+        let remaining: u64 = sample.morsels.values().sum();
+        let carried = sample.carrying as u64;
+        assert_eq!(
+            sample.delivered + remaining + carried,
+            conf.total_storage()
+        );
+        for id in &sample.discovered {
+            assert_eq!(metrics.discoveries()[id], sample.generation);
+        }
     }
 }
+
+#[test]
+fn drive_simulation() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(5);
+    conf.end.push(game::EndCondition::MaxGenerations(500));
+    let total = conf.total_storage();
+
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    let generations = simulation.run_until(|s| s.generation() == 300).unwrap();
+    assert_eq!(generations, 300);
+    assert_eq!(simulation.ants().count(), conf.ants.count);
+    assert_eq!(simulation.nest_location(), conf.nest.location);
+    assert!(simulation.phero_strength(entity::Scent::Colony, (50, 50)) > 0);
+
+    // the food is never lost nor created by the Ants
+    let carried = simulation
+        .ants()
+        .filter(|ant| ant.activity == entity::Activity::Carrying)
+        .count() as u64;
+    let stored = simulation.nest_storage();
+    assert_eq!(simulation.remaining_food() + carried + stored, total);
+
+    let id = simulation.add_morsel((10, 10), 5).unwrap();
+    assert!(simulation.morsels().any(|m| m.id == id && m.storage == 5));
+    assert_eq!(simulation.remaining_food() + carried + stored, total + 5);
+    assert!(simulation.add_morsel((100, 0), 5).is_err());
+
+    simulation.remove_morsel(id).unwrap();
+    assert!(simulation.morsels().all(|m| m.id != id));
+    simulation.step().unwrap();
+    assert!(simulation.remove_morsel(id).is_err());
+
+    simulation.move_nest((20, 20)).unwrap();
+    simulation.step().unwrap();
+    assert_eq!(simulation.nest_location(), (20, 20));
+    assert!(simulation.state().env().entities_at((20, 20)).count() > 0);
+    let generations = simulation.run_until(Simulation::is_over).unwrap();
+    assert!(generations > 0);
+    assert!(simulation.generation() <= 500);
+}
+
+#[test]
+fn end_conditions() {
+    use game::EndCondition::*;
+
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    assert_eq!(conf.end, [AllFood]);
+
+    conf.end = vec![Never, MaxGenerations(200)];
+    let summary = Simulation::new(conf.clone()).unwrap().run().unwrap();
+    assert_eq!(summary.generations, 200);
+    assert_eq!(summary.end, Some(MaxGenerations(200)));
+
+    conf.end = vec![FoodPercentage(10.0)];
+    let summary = Simulation::new(conf.clone()).unwrap().run().unwrap();
+    assert!(summary.collected * 10 >= summary.total);
+    assert!(!summary.completed);
+    assert_eq!(summary.end, Some(FoodPercentage(10.0)));
+
+    conf.end = vec![WallClock(0.0)];
+    let summary = Simulation::new(conf.clone()).unwrap().run().unwrap();
+    assert_eq!(summary.generations, 0);
+
+    conf.set(
+        "end",
+        serde_json::json!(["allFood", { "maxGenerations": 5 }]),
+    )
+    .unwrap();
+    assert_eq!(conf.end, [AllFood, MaxGenerations(5)]);
+
+    // a headless run must end on its own
+    conf.end = vec![Never];
+    assert!(Simulation::new(conf.clone()).unwrap().run().is_err());
+    conf.end.clear();
+    assert!(Simulation::new(conf).is_err());
+}
+
+#[test]
+fn validate_conf() {
+    let conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    assert!(conf.validate().is_ok());
+
+    // no simulation starts with an invalid configuration
+    let mut invalid = conf;
+    invalid.env.dimension = (30, 30);
+    invalid.env.tile_side = 0.0;
+    let error = invalid.validate().unwrap_err().to_string();
+    assert!(error.contains("nest.location"));
+    assert!(error.contains("env.tileSide"));
+    let context = game::Context::new(invalid.clone());
+    assert!(game::State::new(Arc::new(context)).is_err());
+    assert!(Simulation::new(invalid).is_err());
+}
+
+#[test]
+fn layered_conf() {
+    let dir = std::env::temp_dir()
+        .join(format!("formicarium-layered-conf-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let base = std::fs::canonicalize(DEFAULT_CONFIG_PATH).unwrap();
+    let extends = serde_json::json!({
+        "extends": base,
+        "ants": { "count": 5 },
+        "end": [{ "maxGenerations": 10 }],
+    });
+    std::fs::write(dir.join("extends.json"), extends.to_string()).unwrap();
+    std::fs::write(dir.join("partial.json"), r#"{"morsels": {"count": 3}}"#)
+        .unwrap();
+    std::fs::write(dir.join("cycle.json"), r#"{"extends": "cycle.json"}"#)
+        .unwrap();
+
+    let defaults = game::Conf::default();
+    let mut conf = game::Conf::parse(dir.join("partial.json")).unwrap();
+    assert_eq!(conf.morsels.count, 3);
+    assert_eq!(conf.morsels.storage, defaults.morsels.storage);
+    assert_eq!(conf.env.dimension, defaults.env.dimension);
+    assert_eq!(conf.seed, None);
+
+    let base = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    let extended = game::Conf::parse(dir.join("extends.json")).unwrap();
+    assert_eq!(extended.ants.count, 5);
+    assert_eq!(extended.ants.memory_span, base.ants.memory_span);
+    assert_eq!(extended.nest.location, base.nest.location);
+    assert_eq!(extended.end, [game::EndCondition::MaxGenerations(10)]);
+
+    assert!(game::Conf::parse(dir.join("cycle.json")).is_err());
+
+    conf.assign("ants.count=50").unwrap();
+    conf.assign("env.dimension=[40, 20]").unwrap();
+    assert_eq!(conf.ants.count, 50);
+    assert_eq!(conf.env.dimension, (40, 20));
+    assert!(conf.assign("ants.count").is_err());
+    assert!(conf.assign("ants.count=many").is_err());
+
+    // the optional sections not given take their default value
+    conf.assign("ants.energy.capacity=50").unwrap();
+    let energy = conf.ants.energy.unwrap();
+    assert_eq!(energy.capacity, 50);
+    assert_eq!(energy.hunger, game::Energy::default().hunger);
+    assert!(conf.assign("nest.growth.unknown=1").is_err());
+    assert!(conf.assign("ants.sightRadius.x=1").is_err());
+}
+
+#[test]
+fn conf_formats() {
+    let dir = std::env::temp_dir()
+        .join(format!("formicarium-conf-formats-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(7);
+    conf.end.push(game::EndCondition::MaxGenerations(100));
+    let expected = serde_json::to_value(&conf).unwrap();
+
+    for name in ["conf.json", "conf.toml", "conf.yaml"] {
+        let path = dir.join(name);
+        conf.save(&path).unwrap();
+        let parsed = game::Conf::parse(&path).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), expected);
+    }
+    assert!(conf.save(dir.join("conf.txt")).is_err());
+
+    let toml = "extends = \"conf.yaml\"\n[ants]\ncount = 3\n";
+    std::fs::write(dir.join("partial.toml"), toml).unwrap();
+    let partial = game::Conf::parse(dir.join("partial.toml")).unwrap();
+    assert_eq!(partial.ants.count, 3);
+    assert_eq!(partial.end, conf.end);
+}
+
+#[test]
+fn placed_entities() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.morsels.count = 0;
+    conf.assign(
+        r#"morsels.placed=[
+            {"location": [90, 90], "storage": 200},
+            {"location": [55, 50], "storage": 10}
+        ]"#,
+    )
+    .unwrap();
+    conf.assign(
+        r#"pheromones.placed=[
+            {"scent": "food", "location": [52, 50], "concentration": 300}
+        ]"#,
+    )
+    .unwrap();
+    assert_eq!(conf.total_storage(), 210);
+
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    let mut morsels: Vec<_> = simulation
+        .morsels()
+        .map(|m| (m.location, m.storage))
+        .collect();
+    morsels.sort();
+    assert_eq!(morsels, [((55, 50), 10), ((90, 90), 200)]);
+    assert_eq!(
+        simulation.phero_strength(entity::Scent::Food, (52, 50)),
+        300
+    );
+    simulation.run_until(|s| s.generation() == 10).unwrap();
+
+    conf.morsels.placed[1].location = (100, 50);
+    assert!(Simulation::new(conf).is_err());
+}
+
+#[test]
+fn placement_strategies() {
+    use semeion::{Distance, Location};
+
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(11);
+    conf.morsels.placement.min_nest_distance = 5;
+    conf.morsels.placement.min_distance = 2;
+    let nest = Location::from(conf.nest.location);
+
+    let strategies = [
+        game::Strategy::Uniform,
+        game::Strategy::Clustered {
+            patches: 3,
+            radius: 6,
+        },
+        game::Strategy::Ring { distance: 20 },
+        game::Strategy::PoissonDisk { radius: 8 },
+    ];
+    for strategy in strategies {
+        conf.morsels.placement.strategy = strategy;
+        let simulation = Simulation::new(conf.clone()).unwrap();
+        let morsels: Vec<_> = simulation
+            .morsels()
+            .map(|m| Location::from(m.location))
+            .collect();
+        assert_eq!(morsels.len(), conf.morsels.count);
+
+        for (i, &morsel) in morsels.iter().enumerate() {
+            let distance = morsel.distance(nest, Distance::Euclidean);
+            assert!(distance >= 5);
+            if let game::Strategy::Ring { distance: ring } = strategy {
+                assert!(distance.abs_diff(ring) <= 1);
+            }
+            for &other in &morsels[i + 1..] {
+                assert!(morsel.distance(other, Distance::Euclidean) >= 2);
+            }
+        }
+    }
+
+    // there is no room for so many morsels so far apart
+    conf.morsels.placement.strategy =
+        game::Strategy::PoissonDisk { radius: 50 };
+    assert!(Simulation::new(conf).is_err());
+
+    // by default the morsels never share a tile, nor sit on the nest, even
+    // when they cover most of the environment
+    let mut conf = game::Conf {
+        seed: Some(11),
+        ..Default::default()
+    };
+    conf.env.dimension = (10, 10);
+    conf.nest.location = (5, 5);
+    conf.morsels.count = 90;
+    let simulation = Simulation::new(conf.clone()).unwrap();
+    let mut morsels: Vec<_> =
+        simulation.morsels().map(|m| m.location).collect();
+    morsels.sort_unstable();
+    morsels.dedup();
+    assert_eq!(morsels.len(), 90);
+    assert!(!morsels.contains(&(5, 5)));
+}
+
+#[test]
+fn competing_colonies() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(13);
+    conf.assign(
+        r#"colonies=[
+            {"location": [20, 20], "count": 40, "color": [255, 0, 0]},
+            {"location": [80, 80], "count": 20}
+        ]"#,
+    )
+    .unwrap();
+    conf.end = vec![game::EndCondition::MaxGenerations(1000)];
+    let total = conf.total_storage();
+
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    let colonies: Vec<_> = simulation.colonies().collect();
+    assert_eq!(colonies.len(), 2);
+    assert_eq!((colonies[0].location, colonies[0].ants), ((20, 20), 40));
+    assert_eq!((colonies[1].location, colonies[1].ants), ((80, 80), 20));
+    assert_eq!(simulation.ants().count(), 60);
+    assert!(simulation
+        .ants()
+        .all(|ant| { ant.location == colonies[ant.colony].location }));
+
+    let summary = simulation.run().unwrap();
+    assert_eq!(summary.colonies.len(), 2);
+    let collected: u64 = summary.colonies.iter().map(|c| c.delivered).sum();
+    assert_eq!(collected, summary.collected);
+    assert_eq!(summary.total, total);
+    // each colony leaves its own pheromones
+    let strength = |colony| {
+        simulation.colony_phero_strength(
+            colony,
+            entity::Scent::Colony,
+            (20, 20),
+        )
+    };
+    assert!(strength(0) > 0);
+    assert_eq!(
+        strength(0) + strength(1),
+        simulation.phero_strength(entity::Scent::Colony, (20, 20))
+    );
+
+    simulation.move_colony_nest(1, (70, 70)).unwrap();
+    simulation.step().unwrap();
+    assert_eq!(simulation.colonies().nth(1).unwrap().location, (70, 70));
+    assert_eq!(simulation.nest_location(), (20, 20));
+    assert!(simulation.move_colony_nest(2, (70, 70)).is_err());
+
+    // the location of the Nest configured through `nest` is ignored
+    conf.env.dimension = (20, 20);
+    conf.colonies[0].location = (5, 5);
+    conf.colonies[1].location = (15, 15);
+    assert!(Simulation::new(conf.clone()).is_ok());
+
+    // while two Nests never share a tile
+    conf.colonies[1].location = (5, 5);
+    assert!(Simulation::new(conf).is_err());
+}
+
+#[test]
+fn obstacles() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(17);
+    conf.morsels.count = 0;
+    conf.assign(r#"morsels.placed=[{"location": [35, 50], "storage": 20}]"#)
+        .unwrap();
+    conf.assign(
+        r#"obstacles.walls=[
+            {"from": [40, 40], "to": [40, 60]},
+            {"from": [42, 38], "to": [47, 43]}
+        ]"#,
+    )
+    .unwrap();
+    conf.end = vec![game::EndCondition::MaxGenerations(1000)];
+    let walls = conf.obstacles();
+    assert!(walls.contains(&(40, 50)));
+    // diagonal walls have no gaps between their tiles
+    assert!(walls.contains(&(43, 38)) || walls.contains(&(42, 39)));
+    assert_eq!(walls.len(), 21 + 11);
+
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    assert_eq!(simulation.obstacles().count(), walls.len());
+    assert!(simulation.add_morsel((40, 45), 10).is_err());
+    simulation
+        .run_until(|s| {
+            assert!(s.ants().all(|ant| !walls.contains(&ant.location)));
+            s.is_over()
+        })
+        .unwrap();
+    assert!(simulation.nest_storage() > 0);
+
+    let restored = Simulation::restore(simulation.snapshot()).unwrap();
+    assert_eq!(restored.obstacles().count(), walls.len());
+
+    conf.nest.location = (40, 50);
+    assert!(Simulation::new(conf).is_err());
+}
+
+#[test]
+fn map_files() {
+    let dir = std::env::temp_dir()
+        .join(format!("formicarium-map-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let ascii = "\
+##########
+#N...#...#
+#....#.F.#
+#....#...#
+#........#
+#......2.#
+##########
+";
+    std::fs::write(dir.join("map.txt"), ascii).unwrap();
+    let base = std::fs::canonicalize(DEFAULT_CONFIG_PATH).unwrap();
+    let extends = serde_json::json!({
+        "extends": base,
+        "seed": 3,
+        "map": "map.txt",
+        "ants": { "count": 10 },
+        "morsels": { "count": 0, "storage": 20 },
+        "end": [{ "maxGenerations": 2000 }],
+    });
+    std::fs::write(dir.join("conf.json"), extends.to_string()).unwrap();
+
+    let conf = game::Conf::parse(dir.join("conf.json")).unwrap();
+    assert_eq!(conf.map, Some(dir.join("map.txt")));
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    assert_eq!(simulation.conf().env.dimension, (10, 7));
+    assert_eq!(simulation.conf().map, None);
+    // the state only takes configurations with the map already laid out
+    let context = game::Context::new(conf.clone());
+    assert!(game::State::new(Arc::new(context)).is_err());
+    let context = game::Context::new(conf.with_map().unwrap());
+    let state = game::State::new(Arc::new(context)).unwrap();
+    assert_eq!(state.context().conf.env.dimension, (10, 7));
+    assert_eq!(simulation.nest_location(), (1, 1));
+    assert_eq!(simulation.obstacles().count(), 2 * 10 + 2 * 5 + 3);
+    let mut morsels: Vec<_> = simulation
+        .morsels()
+        .map(|m| (m.location, m.storage))
+        .collect();
+    morsels.sort();
+    assert_eq!(morsels, [((7, 2), 20), ((7, 5), 40)]);
+
+    let walls: Vec<_> = simulation.obstacles().collect();
+    simulation
+        .run_until(|s| {
+            assert!(s.ants().all(|ant| !walls.contains(&ant.location)));
+            s.is_over()
+        })
+        .unwrap();
+    assert!(simulation.nest_storage() > 0);
+
+    // black walls, red nests, green food and white or transparent empty tiles
+    let pixels = [
+        [0, 0, 0, 255, 255, 0, 0, 255, 0, 40, 0, 255],
+        [255, 255, 255, 255, 9, 9, 9, 0, 0, 0, 0, 255],
+    ];
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, 3, 2);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&pixels.concat()).unwrap();
+    drop(writer);
+    let map = game::Map::read_png(png.as_slice()).unwrap();
+    assert_eq!(
+        map,
+        game::Map {
+            dimension: (3, 2),
+            walls: vec![(0, 0), (2, 1)],
+            nests: vec![(1, 0)],
+            morsels: vec![((2, 0), 40)],
+        }
+    );
+
+    // the 16 bit channels are read as their most significant byte
+    let pixels = [
+        [0, 0, 0, 0, 0, 0, 255, 255, 0, 0, 0, 0, 0, 0, 40, 255, 0, 0],
+        [
+            255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ],
+    ];
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, 3, 2);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&pixels.concat()).unwrap();
+    drop(writer);
+    let map = game::Map::read_png(png.as_slice()).unwrap();
+    assert_eq!(
+        map,
+        game::Map {
+            dimension: (3, 2),
+            walls: vec![(0, 0), (1, 1), (2, 1)],
+            nests: vec![(1, 0)],
+            morsels: vec![((2, 0), 40)],
+        }
+    );
+
+    assert!(game::Map::parse_ascii("#N?", 10).is_err());
+    let mut invalid = conf;
+    invalid.map = Some(dir.join("missing.txt"));
+    assert!(Simulation::new(invalid).is_err());
+}
+
+#[test]
+fn bounded_topology() {
+    use semeion::{Distance, Offset};
+
+    let dimension = (30, 20);
+    let torus = game::Topology::Torus;
+    let reflective = game::Topology::Reflective;
+    let blocking = game::Topology::Blocking;
+    let (corner, opposite) = ((0, 0).into(), (29, 19).into());
+    let offset = Offset { x: -1, y: 2 };
+    assert_eq!(torus.constrain(corner, offset, dimension), offset);
+    assert_eq!(
+        reflective.constrain(corner, offset, dimension),
+        Offset { x: 1, y: 2 }
+    );
+    assert_eq!(
+        blocking.constrain(corner, offset, dimension),
+        Offset { x: 0, y: 2 }
+    );
+    assert!(!blocking.contains(corner, offset, dimension));
+    assert_eq!(torus.translate(corner, offset, dimension), (29, 2).into());
+    assert_eq!(
+        torus.distance(corner, opposite, dimension, Distance::Manhattan),
+        2
+    );
+    assert_eq!(
+        blocking.distance(corner, opposite, dimension, Distance::Manhattan),
+        48
+    );
+    assert_eq!(
+        torus.step(corner, opposite, dimension),
+        Offset { x: -1, y: -1 }
+    );
+    assert_eq!(
+        reflective.step(corner, opposite, dimension),
+        Offset { x: 1, y: 1 }
+    );
+
+    for topology in ["reflective", "blocking"] {
+        let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+        conf.seed = Some(5);
+        conf.assign("env.dimension=[30, 20]").unwrap();
+        conf.assign("nest.location=[1, 1]").unwrap();
+        conf.assign(&format!("env.topology={}", topology)).unwrap();
+        conf.ants.count = 40;
+        conf.morsels.count = 5;
+        conf.end = vec![game::EndCondition::MaxGenerations(500)];
+
+        let mut simulation = Simulation::new(conf).unwrap();
+        let locations = |s: &Simulation| {
+            s.ants()
+                .map(|ant| (ant.id, ant.location))
+                .collect::<std::collections::HashMap<_, _>>()
+        };
+        let mut previous = locations(&simulation);
+        simulation
+            .run_until(|s| {
+                // the Ants never cross the edges of the environment
+                for ant in s.ants() {
+                    let (x, y) = previous[&ant.id];
+                    assert!((ant.location.0 - x).abs() <= 1);
+                    assert!((ant.location.1 - y).abs() <= 1);
+                }
+                previous = locations(s);
+                s.is_over()
+            })
+            .unwrap();
+        assert!(simulation.nest_storage() > 0);
+    }
+}
+
+#[test]
+fn pheromone_models() {
+    use game::Evaporation;
+
+    assert_eq!(Evaporation::Linear(1).evaporate(10), 9);
+    assert_eq!(Evaporation::Linear(4).evaporate(3), 0);
+    assert_eq!(Evaporation::Exponential(0.5).evaporate(10), 5);
+    // at least a unit is always lost
+    assert_eq!(Evaporation::Exponential(0.01).evaporate(10), 9);
+    assert_eq!(Evaporation::HalfLife(1.0).evaporate(10), 5);
+    assert_eq!(Evaporation::HalfLife(2.0).evaporate(100), 71);
+
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(19);
+    conf.ants.count = 0;
+    conf.morsels.count = 0;
+    conf.end = vec![game::EndCondition::Never];
+    conf.assign(r#"pheromones.food.evaporation={"exponential": 0.1}"#)
+        .unwrap();
+    conf.assign("pheromones.food.diffusion=0.4").unwrap();
+    conf.assign(
+        r#"pheromones.placed=[
+            {"scent": "food", "location": [20, 20], "concentration": 1000},
+            {"scent": "colony", "location": [30, 30], "concentration": 1000}
+        ]"#,
+    )
+    .unwrap();
+
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    simulation.step().unwrap();
+    let food = |s: &Simulation, location| {
+        s.phero_strength(entity::Scent::Food, location)
+    };
+    // 900 left after evaporation, 45 of which spread to each adjacent tile
+    assert_eq!(food(&simulation, (20, 20)), 900 - 8 * 45);
+    assert_eq!(food(&simulation, (21, 19)), 45);
+    assert_eq!(
+        simulation.phero_strength(entity::Scent::Colony, (30, 30)),
+        999
+    );
+    assert_eq!(
+        simulation.phero_strength(entity::Scent::Colony, (31, 30)),
+        0
+    );
+    simulation.run_until(|s| s.generation() == 10).unwrap();
+    assert!(food(&simulation, (22, 22)) > 0);
+    assert!(food(&simulation, (20, 20)) > food(&simulation, (22, 22)));
+
+    // the replay evaporates and spreads the pheromones in the same way
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(23);
+    conf.assign(r#"pheromones.colony.evaporation={"halfLife": 50}"#)
+        .unwrap();
+    conf.assign("pheromones.colony.diffusion=0.2").unwrap();
+    conf.assign("pheromones.food.diffusion=0.1").unwrap();
+    let context = game::Context::new(conf.clone());
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    record_and_replay(&mut state, "diffusion", |s| s.generation() < 300);
+}
+
+#[test]
+fn dense_pheromones() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(20);
+    conf.ants.count = 10;
+    conf.morsels.count = 0;
+    conf.morsels.placed = vec![game::PlacedMorsel {
+        location: (40, 40),
+        storage: 10,
+    }];
+    conf.end = vec![game::EndCondition::Never];
+
+    // all the Ants leave the Nest together, each adding its pheromone to the
+    // single concentration of the tile
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    let nest = simulation.nest_location();
+    simulation.step().unwrap();
+    let deposit = conf.ants.max_phero_concentration - conf.ants.phero_decrease;
+    assert_eq!(
+        simulation.phero_strength(entity::Scent::Colony, nest),
+        10 * deposit as u64
+    );
+    let neighbor = (nest.0 + 1, nest.1);
+    assert_eq!(
+        simulation.phero_strength(entity::Scent::Colony, neighbor),
+        0
+    );
+
+    // deposits add up, suppressed pheromones are cleared before them, and
+    // the ones outside the environment are ignored
+    let mut field = game::PheroField::new(&conf);
+    let scent = entity::Scent::Food;
+    let location = semeion::Location { x: 3, y: 4 };
+    let outside = semeion::Location { x: -1, y: 4 };
+    field.set(scent, 0, location, 100);
+    let traces = game::Traces {
+        deposits: vec![
+            ((scent, 0, location), 5),
+            ((scent, 0, location), 7),
+            ((scent, 0, outside), 7),
+        ],
+        suppressed: vec![(scent, 0, location)],
+    };
+    field.update(&conf, &traces);
+    assert_eq!(field.get(scent, 0, location), 12);
+    assert_eq!(field.get(entity::Scent::Colony, 0, location), 0);
+    assert_eq!(field.pheros().count(), 1);
+    field.update(&conf, &game::Traces::default());
+    assert_eq!(field.get(scent, 0, location), 11);
+}
+
+#[test]
+fn perception_radius() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(21);
+    conf.ants.count = 1;
+    conf.morsels.count = 0;
+    conf.end = vec![game::EndCondition::Never];
+
+    // the Ant walks straight to the Morsel it sees from the Nest
+    let mut sight = conf.clone();
+    sight.assign("ants.perceptionRadius=3").unwrap();
+    sight
+        .assign(r#"morsels.placed=[{"location": [53, 50], "storage": 5}]"#)
+        .unwrap();
+    let mut simulation = Simulation::new(sight).unwrap();
+    simulation.run_until(|s| s.generation() == 3).unwrap();
+    let ant = simulation.ants().next().unwrap();
+    assert_eq!(ant.location, (53, 50));
+
+    // the scent of the pheromones fades with their distance
+    let smell = |concentration| {
+        let mut conf = conf.clone();
+        conf.assign("ants.smellRadius=2").unwrap();
+        conf.pheromones.placed = vec![
+            game::PlacedPhero {
+                scent: entity::Scent::Food,
+                colony: 0,
+                location: (52, 50),
+                concentration: 400,
+            },
+            game::PlacedPhero {
+                scent: entity::Scent::Food,
+                colony: 0,
+                location: (49, 50),
+                concentration,
+            },
+        ];
+        let mut simulation = Simulation::new(conf).unwrap();
+        simulation.step().unwrap();
+        let ant = simulation.ants().next().unwrap();
+        ant.location
+    };
+    assert_eq!(smell(150), (51, 50));
+    assert_eq!(smell(250), (49, 50));
+}
+
+#[test]
+fn transition_rule() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.ants.count = 1;
+    conf.morsels.count = 0;
+    conf.end = vec![game::EndCondition::Never];
+    conf.pheromones.placed = [((51, 50), 100), ((49, 50), 300)]
+        .iter()
+        .map(|&(location, concentration)| game::PlacedPhero {
+            scent: entity::Scent::Food,
+            colony: 0,
+            location,
+            concentration,
+        })
+        .collect();
+
+    // count where the Ant moves from the Nest with different seeds
+    let choices = |conf: &game::Conf| {
+        let mut choices = std::collections::HashMap::new();
+        for seed in 0..200 {
+            let mut conf = conf.clone();
+            conf.seed = Some(seed);
+            let mut simulation = Simulation::new(conf).unwrap();
+            simulation.step().unwrap();
+            let ant = simulation.ants().next().unwrap();
+            *choices.entry(ant.location).or_insert(0) += 1;
+        }
+        choices
+    };
+
+    // the greedy Ants always follow the strongest scent
+    assert_eq!(choices(&conf)[&(49, 50)], 200);
+
+    // while each tile has a chance proportional to its concentration
+    conf.assign(r#"ants.transition={"probabilistic": {"alpha": 1}}"#)
+        .unwrap();
+    let probabilistic = choices(&conf);
+    assert_eq!(probabilistic.values().sum::<usize>(), 200);
+    assert!(probabilistic[&(51, 50)] > 20);
+    assert!(probabilistic[&(49, 50)] > 2 * probabilistic[&(51, 50)]);
+
+    // each colony may follow its own rule
+    conf.assign(
+        r#"colonies=[
+            {"location": [50, 50], "count": 1},
+            {"location": [20, 20], "count": 1, "transition": "greedy"}
+        ]"#,
+    )
+    .unwrap();
+    assert_ne!(conf.transition(0), game::Transition::Greedy);
+    assert_eq!(conf.transition(1), game::Transition::Greedy);
+}
+
+#[test]
+fn ant_energy() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(23);
+    conf.ants.count = 1;
+    conf.ants.perception_radius = 3;
+    conf.morsels.count = 0;
+    conf.morsels.placed = vec![game::PlacedMorsel {
+        location: (53, 50),
+        storage: 10,
+    }];
+    conf.end = vec![game::EndCondition::Never];
+    conf.assign(r#"ants.energy={"capacity": 4, "hunger": 0.9}"#)
+        .unwrap();
+
+    // the hungry Ant eats from the Morsel it reaches, before taking food from
+    // it, and spends its energy again as soon as it moves
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    simulation.run_until(|s| s.generation() == 3).unwrap();
+    assert_eq!(simulation.ants().next().unwrap().energy, Some(1));
+    simulation.step().unwrap();
+    let ant = simulation.ants().next().unwrap();
+    assert_eq!(ant.activity, entity::ant::Activity::Carrying);
+    assert_eq!(ant.energy, Some(3));
+    assert_eq!(simulation.morsels().next().unwrap().storage, 8);
+
+    // the food eaten from the Nest once delivered still counts as collected
+    conf.end = vec![game::EndCondition::FoodPercentage(100.0)];
+    conf.assign(r#"ants.energy={"capacity": 100, "hunger": 1}"#)
+        .unwrap();
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    let summary = simulation.run().unwrap();
+    let colony = simulation.colonies().next().unwrap();
+    assert_eq!(summary.end, Some(game::EndCondition::FoodPercentage(100.0)));
+    assert_eq!((summary.collected, summary.total), (5, 5));
+    assert_eq!((colony.delivered, colony.storage), (5, 0));
+
+    // the Ants that cannot find any food starve to death
+    conf.ants.count = 5;
+    conf.ants.perception_radius = 1;
+    conf.morsels.placed.clear();
+    conf.assign(r#"ants.energy={"capacity": 20}"#).unwrap();
+    let context = game::Context::new(conf.clone());
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    state.collect_metrics(None);
+    let replay = record_and_replay(&mut state, "energy", |s| {
+        assert!(s.generation() < 1000);
+        s.colonies()[0].ants > 0
+    });
+    assert_eq!(state.colonies()[0].deaths, 5);
+    assert_eq!(state.metrics().unwrap().latest().deaths, 5);
+    assert_eq!(game::Summary::new(&state).colonies[0].deaths, 5);
+    let restored = game::State::restore(state.snapshot()).unwrap();
+    assert_eq!(restored.colonies()[0].deaths, 5);
+
+    assert!(replay.frame().ants.is_empty());
+    assert_eq!(replay.frame().deaths, 5);
+}
+
+#[test]
+fn colony_growth() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(24);
+    conf.ants.count = 5;
+    conf.morsels.count = 0;
+    conf.morsels.placed = vec![game::PlacedMorsel {
+        location: (51, 50),
+        storage: 30,
+    }];
+    conf.assign(r#"nest.growth={"foodPerAnt": 5, "maxPopulation": 8}"#)
+        .unwrap();
+
+    // the Nest spawns an Ant for every 5 units of food delivered, until the
+    // colony reaches 8 Ants, and keeps the rest of the food, while the food
+    // delivered so far doesn't decrease
+    let context = game::Context::new(conf.clone());
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    state.collect_metrics(None);
+    let replay = record_and_replay(&mut state, "growth", |s| {
+        assert!(s.generation() < 10000);
+        !s.is_simulation_over()
+    });
+    let colony = state.colonies()[0];
+    assert_eq!((colony.ants, colony.births, colony.delivered), (8, 3, 30));
+    assert_eq!(state.colony_storages(), [15]);
+    assert_eq!(state.context().population(0), 8);
+    assert_eq!(state.metrics().unwrap().latest().births, 3);
+    let restored = game::State::restore(state.snapshot()).unwrap();
+    assert_eq!(restored.colonies()[0], colony);
+
+    assert_eq!(replay.frame().ants.len(), 8);
+    assert_eq!((replay.frame().births, replay.frame().delivered), (3, 30));
+}
+
+#[test]
+fn food_dynamics() {
+    let mut conf = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    conf.seed = Some(25);
+    conf.ants.count = 0;
+    conf.morsels.count = 0;
+    conf.morsels.storage = 4;
+    conf.morsels.placed = vec![game::PlacedMorsel {
+        location: (10, 10),
+        storage: 5,
+    }];
+    conf.end = vec![game::EndCondition::Never];
+    conf.assign(r#"morsels.regrowth={"amount": 2, "interval": 3, "cap": 10}"#)
+        .unwrap();
+    conf.assign(r#"morsels.spawning={"schedule": [2, 2, 5]}"#)
+        .unwrap();
+
+    // the Morsels regrow every 3 generations up to the cap, while the new
+    // ones appear as scheduled
+    let context = game::Context::new(conf.clone());
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    // the storages of the Morsels in each generation
+    let mut storages = Vec::new();
+    let replay = record_and_replay(&mut state, "food", |s| {
+        storages.push(morsel_storages(s));
+        s.generation() < 12
+    });
+    assert_eq!(storages[1], [5]);
+    assert_eq!(storages[2], [5, 4, 4]);
+    assert_eq!(storages[3], [7, 6, 6]);
+    assert_eq!(storages[5], [7, 6, 6, 4]);
+    assert_eq!(storages[6], [9, 8, 8, 6]);
+    assert_eq!(storages[12], [10, 10, 10, 10]);
+    let restored = game::State::restore(state.snapshot()).unwrap();
+    assert_eq!(restored.total_storage(), 40);
+
+    let morsels = replay.frame().morsels.values();
+    let frame: Vec<_> = morsels.map(|&(_, storage)| storage).collect();
+    assert_eq!(frame, [10, 10, 10, 10]);
+
+    // the Morsels emptied by the Ants stay in the environment, and regrow
+    let mut emptied = game::Conf::parse(DEFAULT_CONFIG_PATH).unwrap();
+    emptied.seed = Some(25);
+    emptied.morsels.count = 0;
+    emptied.morsels.placed = vec![game::PlacedMorsel {
+        location: (52, 50),
+        storage: 3,
+    }];
+    emptied
+        .assign(r#"morsels.regrowth={"amount": 1, "interval": 50, "cap": 3}"#)
+        .unwrap();
+    let context = game::Context::new(emptied);
+    let mut state = game::State::new(Arc::new(context)).unwrap();
+    let mut storages = Vec::new();
+    let replay = record_and_replay(&mut state, "regrowth", |s| {
+        storages.push(morsel_storages(s));
+        s.generation() < 300
+    });
+    assert!(storages.iter().all(|storages| storages.len() == 1));
+    let empty = storages.iter().position(|s| s == &[0]).unwrap();
+    assert!(storages[empty..].iter().any(|s| s[0] > 0));
+    assert_eq!(replay.frame().morsels.len(), 1);
+
+    // with a rate of 1 a new Morsel appears at each generation
+    conf.morsels.regrowth = None;
+    conf.assign(r#"morsels.spawning={"rate": 1, "storage": 3}"#)
+        .unwrap();
+    let mut simulation = Simulation::new(conf.clone()).unwrap();
+    simulation.run_until(|s| s.generation() == 10).unwrap();
+    assert_eq!(simulation.morsels().count(), 11);
+    assert!(simulation.morsels().skip(1).all(|m| m.storage == 3));
+}